@@ -0,0 +1,240 @@
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A calendar date, like `2024-02-29`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: u16,
+    /// From 1 to 12.
+    pub month: u8,
+    /// From 1 to the number of days in the month.
+    pub day: u8,
+}
+
+/// A time of day, like `09:30:00.25`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Time {
+    /// From 0 to 23.
+    pub hour: u8,
+    /// From 0 to 59.
+    pub minute: u8,
+    /// From 0 to 60, where 60 is a leap second.
+    pub second: u8,
+    pub nanosecond: u32,
+}
+
+/// The offset of a date-time from UTC, where `Z` is an offset of zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Offset {
+    /// Minutes ahead of UTC, or behind it if negative.
+    pub minutes: i16,
+}
+
+/// A date and time as in RFC 3339, like `2024-02-29T09:30:00Z`. Without an offset it's a local
+/// date-time, which isn't a point in time until it's given a time zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DateTime {
+    pub date: Date,
+    pub time: Time,
+    pub offset: Option<Offset>,
+}
+
+impl Date {
+    /// The date, if the month and day are valid.
+    pub fn new(year: u16, month: u8, day: u8) -> Option<Self> {
+        if (1..=12).contains(&month) && day >= 1 && day <= days_in_month(year, month) {
+            Some(Self { year, month, day })
+        } else {
+            None
+        }
+    }
+
+    /// Days since 1970-01-01.
+    fn days_since_epoch(self) -> i64 {
+        // Howard Hinnant's `days_from_civil`, with years starting in March so leap days come last.
+        let month = i64::from(self.month);
+        let year = i64::from(self.year) - i64::from(month <= 2);
+        let era = year.div_euclid(400);
+        let year_of_era = year.rem_euclid(400);
+        let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + i64::from(self.day) - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+        era * 146_097 + day_of_era - 719_468
+    }
+}
+
+impl Time {
+    /// The time, if each field is in range.
+    pub fn new(hour: u8, minute: u8, second: u8, nanosecond: u32) -> Option<Self> {
+        if hour < 24 && minute < 60 && second <= 60 && nanosecond < 1_000_000_000 {
+            Some(Self {
+                hour,
+                minute,
+                second,
+                nanosecond,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl Offset {
+    pub const UTC: Offset = Offset { minutes: 0 };
+
+    /// The offset, if it's less than a day.
+    pub fn new(minutes: i16) -> Option<Self> {
+        if minutes > -24 * 60 && minutes < 24 * 60 {
+            Some(Self { minutes })
+        } else {
+            None
+        }
+    }
+}
+
+impl DateTime {
+    /// The point in time, or `None` for a local date-time or one outside the range of
+    /// `SystemTime` on this platform.
+    pub fn to_system_time(&self) -> Option<SystemTime> {
+        let offset = self.offset?;
+        let seconds = self.date.days_since_epoch() * 86_400
+            + i64::from(self.time.hour) * 3600
+            + i64::from(self.time.minute) * 60
+            + i64::from(self.time.second)
+            - i64::from(offset.minutes) * 60;
+        let nanoseconds = Duration::from_nanos(self.time.nanosecond.into());
+
+        if seconds >= 0 {
+            UNIX_EPOCH.checked_add(Duration::from_secs(seconds as u64) + nanoseconds)
+        } else {
+            UNIX_EPOCH
+                .checked_sub(Duration::from_secs(seconds.unsigned_abs()))?
+                .checked_add(nanoseconds)
+        }
+    }
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl fmt::Display for Time {
+    /// Writes the fraction of a second only if there is one, with as few digits as it needs.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}:{:02}", self.hour, self.minute, self.second)?;
+
+        if self.nanosecond != 0 {
+            let fraction = format!("{:09}", self.nanosecond);
+            write!(f, ".{}", fraction.trim_end_matches('0'))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Offset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.minutes == 0 {
+            return f.write_str("Z");
+        }
+
+        let sign = if self.minutes < 0 { '-' } else { '+' };
+        let minutes = self.minutes.unsigned_abs();
+        write!(f, "{}{:02}:{:02}", sign, minutes / 60, minutes % 60)
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}T{}", self.date, self.time)?;
+
+        match self.offset {
+            Some(offset) => write!(f, "{}", offset),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date_time(date: (u16, u8, u8), time: (u8, u8, u8, u32), offset: i16) -> DateTime {
+        DateTime {
+            date: Date::new(date.0, date.1, date.2).unwrap(),
+            time: Time::new(time.0, time.1, time.2, time.3).unwrap(),
+            offset: Offset::new(offset),
+        }
+    }
+
+    #[test]
+    fn validation() {
+        assert!(Date::new(2024, 2, 29).is_some());
+        assert!(Date::new(2023, 2, 29).is_none());
+        assert!(Date::new(2000, 2, 29).is_some());
+        assert!(Date::new(1900, 2, 29).is_none());
+        assert!(Date::new(2024, 4, 31).is_none());
+        assert!(Date::new(2024, 13, 1).is_none());
+        assert!(Date::new(2024, 1, 0).is_none());
+
+        assert!(Time::new(23, 59, 60, 999_999_999).is_some());
+        assert!(Time::new(24, 0, 0, 0).is_none());
+        assert!(Time::new(0, 60, 0, 0).is_none());
+
+        assert!(Offset::new(-(23 * 60 + 59)).is_some());
+        assert!(Offset::new(24 * 60).is_none());
+    }
+
+    #[test]
+    fn to_system_time() {
+        assert_eq!(
+            date_time((1970, 1, 1), (0, 0, 0, 0), 0).to_system_time(),
+            Some(UNIX_EPOCH)
+        );
+        assert_eq!(
+            date_time((2024, 2, 29), (12, 30, 15, 500_000_000), 0).to_system_time(),
+            Some(UNIX_EPOCH + Duration::new(1_709_209_815, 500_000_000))
+        );
+        // The same instant written with a different offset.
+        assert_eq!(
+            date_time((2024, 2, 29), (14, 0, 15, 500_000_000), 90).to_system_time(),
+            Some(UNIX_EPOCH + Duration::new(1_709_209_815, 500_000_000))
+        );
+        assert_eq!(
+            date_time((1969, 12, 31), (23, 59, 59, 250_000_000), 0).to_system_time(),
+            Some(UNIX_EPOCH - Duration::from_millis(750))
+        );
+
+        let local = DateTime {
+            offset: None,
+            ..date_time((2024, 1, 1), (0, 0, 0, 0), 0)
+        };
+        assert_eq!(local.to_system_time(), None);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            date_time((2024, 2, 9), (8, 5, 3, 0), 0).to_string(),
+            "2024-02-09T08:05:03Z"
+        );
+        assert_eq!(
+            date_time((2024, 2, 9), (8, 5, 3, 120_000_000), -330).to_string(),
+            "2024-02-09T08:05:03.12-05:30"
+        );
+        assert_eq!(Date::new(7, 1, 1).unwrap().to_string(), "0007-01-01");
+    }
+}
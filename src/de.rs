@@ -0,0 +1,677 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Unexpected, Visitor};
+use serde::forward_to_deserialize_any;
+use serde::Deserialize;
+
+use crate::{parse_with_spans, Error, Path, PathSegment, Position, Spans, Value};
+
+/// Deserializes a `T` from ooml text. Strings without escapes are borrowed from the input, so `T`
+/// can have `&str` fields, and errors have the position of the value that couldn't be
+/// deserialized.
+///
+/// Dates and times deserialize as strings in RFC 3339 form, durations as
+/// [`std::time::Duration`], and byte sizes as integers.
+pub fn from_str<'de, T: Deserialize<'de>>(input: &'de str) -> Result<T, Error> {
+    let (value, spans) = parse_with_spans(input)?;
+    let source = Source { input, spans };
+
+    T::deserialize(Deserializer {
+        value: &value,
+        path: Path::new(),
+        source: Some(&source),
+    })
+}
+
+/// Deserializes a `T` from a parsed value, like [`from_str`]. Errors have no position, as there's
+/// no input to point into.
+pub fn from_value<'de, T: Deserialize<'de>>(value: &Value<'de>) -> Result<T, Error> {
+    T::deserialize(Deserializer {
+        value,
+        path: Path::new(),
+        source: None,
+    })
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Error::Deserialize {
+            message: message.to_string(),
+            position: None,
+        }
+    }
+}
+
+/// The input a value was parsed from, with where each value in it starts.
+struct Source<'de> {
+    input: &'de str,
+    spans: Spans<'de>,
+}
+
+struct Deserializer<'a, 'de> {
+    value: &'a Value<'de>,
+    /// The path to `value`, which is only kept when there's a source to find its position in.
+    path: Path<'de>,
+    source: Option<&'a Source<'de>>,
+}
+
+impl<'a, 'de> Deserializer<'a, 'de> {
+    /// A deserializer for `value`, found at `segment` in this one's value.
+    fn nested(&self, value: &'a Value<'de>, segment: PathSegment<'de>) -> Self {
+        let path = match self.source {
+            Some(_) => {
+                let mut path = self.path.clone();
+                path.push(segment);
+                path
+            }
+            None => Path::new(),
+        };
+
+        Deserializer {
+            value,
+            path,
+            source: self.source,
+        }
+    }
+
+    fn position(&self) -> Option<Position> {
+        let source = self.source?;
+        let span = source.spans.get(&self.path)?;
+
+        Some(Position::new(source.input, source.input.len() - span.len()))
+    }
+
+    /// Gives an error from deserializing this value its position, unless it already has one from
+    /// a value nested in this one.
+    fn locate(&self, error: Error) -> Error {
+        match error {
+            Error::Deserialize {
+                message,
+                position: None,
+            } => Error::Deserialize {
+                message,
+                position: self.position(),
+            },
+            error => error,
+        }
+    }
+
+    fn visit_array<V: Visitor<'de>>(
+        &self,
+        array: &'a [Value<'de>],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let mut items = SeqAccess {
+            parent: self,
+            items: array.iter().enumerate(),
+        };
+        let value = visitor.visit_seq(&mut items)?;
+
+        match items.items.next() {
+            Some((i, _)) => Err(de::Error::invalid_length(
+                array.len(),
+                &format!("{} items", i).as_str(),
+            )),
+            None => Ok(value),
+        }
+    }
+}
+
+/// How a value is described in errors about it being the wrong type.
+fn unexpected<'a>(value: &'a Value<'_>) -> Unexpected<'a> {
+    match value {
+        Value::Null => Unexpected::Unit,
+        Value::String(string) => Unexpected::Str(string),
+        Value::Integer(integer) => Unexpected::Signed(*integer),
+        Value::Float(float) => Unexpected::Float(*float),
+        Value::Bool(bool) => Unexpected::Bool(*bool),
+        Value::DateTime(_) => Unexpected::Other("date-time"),
+        Value::Date(_) => Unexpected::Other("date"),
+        Value::Time(_) => Unexpected::Other("time"),
+        Value::Duration(_) => Unexpected::Other("duration"),
+        Value::Bytes(_) => Unexpected::Other("byte size"),
+        Value::Object(_) => Unexpected::Map,
+        Value::Array(_) => Unexpected::Seq,
+    }
+}
+
+impl<'a, 'de> de::Deserializer<'de> for Deserializer<'a, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let result = match self.value {
+            Value::Null => visitor.visit_unit(),
+            Value::String(Cow::Borrowed(string)) => visitor.visit_borrowed_str(string),
+            Value::String(Cow::Owned(string)) => visitor.visit_str(string),
+            Value::Integer(integer) => visitor.visit_i64(*integer),
+            Value::Float(float) => visitor.visit_f64(*float),
+            Value::Bool(bool) => visitor.visit_bool(*bool),
+            Value::DateTime(date_time) => visitor.visit_string(date_time.to_string()),
+            Value::Date(date) => visitor.visit_string(date.to_string()),
+            Value::Time(time) => visitor.visit_string(time.to_string()),
+            // The same form `Duration`'s own `Deserialize` implementation takes.
+            Value::Duration(duration) => visitor.visit_seq(de::value::SeqDeserializer::new(
+                vec![duration.as_secs(), u64::from(duration.subsec_nanos())].into_iter(),
+            )),
+            Value::Bytes(bytes) => visitor.visit_u64(*bytes),
+            Value::Object(object) => visitor.visit_map(MapAccess {
+                parent: &self,
+                entries: object.iter(),
+                value: None,
+            }),
+            Value::Array(array) => self.visit_array(array, visitor),
+        };
+
+        result.map_err(|e| self.locate(e))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.value {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Enums are externally tagged: a unit variant is a string, and any other variant is an
+    /// object with the variant as its only key.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let single_entry = match self.value {
+            Value::Object(object) if object.len() == 1 => object.get_index(0),
+            _ => None,
+        };
+        let result = match (self.value, single_entry) {
+            (Value::String(variant), _) => visitor.visit_enum(Enum {
+                parent: &self,
+                variant,
+                content: None,
+            }),
+            (_, Some((variant, content))) => visitor.visit_enum(Enum {
+                parent: &self,
+                variant,
+                content: Some(content),
+            }),
+            (value, None) => Err(de::Error::invalid_type(
+                unexpected(value),
+                &"a string or an object with a single key",
+            )),
+        };
+
+        result.map_err(|e| self.locate(e))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf unit
+        unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+struct MapAccess<'b, 'a, 'de> {
+    parent: &'b Deserializer<'a, 'de>,
+    entries: indexmap::map::Iter<'a, Cow<'de, str>, Value<'de>>,
+    /// The entry whose key was deserialized last.
+    value: Option<(&'a Cow<'de, str>, &'a Value<'de>)>,
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess<'_, '_, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let (key, value) = match self.entries.next() {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        self.value = Some((key, value));
+
+        // Errors about the key, like it being an unknown field, point at the entry's value.
+        seed.deserialize(Key(key)).map(Some).map_err(|e| {
+            self.parent
+                .nested(value, PathSegment::Key(key.clone()))
+                .locate(e)
+        })
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        match self.value.take() {
+            Some((key, value)) => {
+                seed.deserialize(self.parent.nested(value, PathSegment::Key(key.clone())))
+            }
+            None => Err(de::Error::custom("value requested before its key")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+struct SeqAccess<'b, 'a, 'de> {
+    parent: &'b Deserializer<'a, 'de>,
+    items: std::iter::Enumerate<std::slice::Iter<'a, Value<'de>>>,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess<'_, '_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        match self.items.next() {
+            Some((i, item)) => seed
+                .deserialize(self.parent.nested(item, PathSegment::Index(i)))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+/// An object key or enum variant, which is borrowed from the input when it can be.
+struct Key<'a, 'de>(&'a Cow<'de, str>);
+
+/// Deserializers for keys of types other than strings, from the key's text. These are the keys
+/// that the serializer writes as their text, like `1` for a map with integer keys.
+macro_rules! parse_key {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match self.0.parse() {
+                    Ok(key) => visitor.$visit(key),
+                    Err(_) => Err(de::Error::invalid_type(Unexpected::Str(self.0), &visitor)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Key<'_, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Cow::Borrowed(key) => visitor.visit_borrowed_str(key),
+            Cow::Owned(key) => visitor.visit_str(key),
+        }
+    }
+
+    /// Keys can be unit variants, e.g. of an enum used as the key type of a map.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let key: de::value::StrDeserializer<Error> = self.0.as_ref().into_deserializer();
+        key.deserialize_enum(name, variants, visitor)
+    }
+
+    parse_key! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+    }
+
+    forward_to_deserialize_any! {
+        f32 f64 char str string bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct Enum<'b, 'a, 'de> {
+    parent: &'b Deserializer<'a, 'de>,
+    variant: &'a Cow<'de, str>,
+    /// The value under the variant's key, or `None` for a unit variant written as a string.
+    content: Option<&'a Value<'de>>,
+}
+
+impl<'a, 'de> Enum<'_, 'a, 'de> {
+    fn content(&self, expected: &str) -> Result<Deserializer<'a, 'de>, Error> {
+        match self.content {
+            Some(content) => Ok(self
+                .parent
+                .nested(content, PathSegment::Key(self.variant.clone()))),
+            None => Err(de::Error::invalid_type(Unexpected::UnitVariant, &expected)),
+        }
+    }
+}
+
+impl<'b, 'a, 'de> de::EnumAccess<'de> for Enum<'b, 'a, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+        let variant = seed.deserialize(Key(self.variant))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Enum<'_, '_, 'de> {
+    type Error = Error;
+
+    /// A unit variant can also be written as an object with a null value, like `Variant: ~`.
+    fn unit_variant(self) -> Result<(), Error> {
+        match self.content {
+            None | Some(Value::Null) => Ok(()),
+            Some(content) => Err(de::Error::invalid_type(
+                unexpected(content),
+                &"unit variant",
+            )),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self.content("newtype variant")?)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(self.content("tuple variant")?, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_map(self.content("struct variant")?, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    use indoc::indoc;
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::parse;
+
+    fn position(offset: usize, line: usize, column: usize) -> Option<Position> {
+        Some(Position {
+            offset,
+            line,
+            column,
+        })
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config<'a> {
+        name: &'a str,
+        description: String,
+        port: u16,
+        ratio: f64,
+        debug: bool,
+        timeout: Duration,
+        cache: u64,
+        started: String,
+        missing: Option<u32>,
+        nothing: Option<u32>,
+        servers: Vec<Server>,
+        labels: BTreeMap<String, String>,
+        mode: Mode,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Server {
+        host: String,
+        weight: Option<u8>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Mode {
+        Off,
+        Fixed(u32),
+        Range(u32, u32),
+        Custom { min: u32, max: u32 },
+    }
+
+    #[test]
+    fn config() {
+        let input = indoc! {r#"
+            name: "api"
+            description: "line\nbreak"
+            port: 8080
+            ratio: 1
+            debug: true
+            timeout: 1m30s
+            cache: 64MiB
+            started: 2024-02-29T09:30:00Z
+            nothing: ~
+            servers:
+              - host: "a"
+                weight: 2
+              - { host: "b" }
+            labels.team: "core"
+            labels.tier: "1"
+            mode:
+              Custom: { min: 1, max: 5 }
+        "#};
+
+        assert_eq!(
+            from_str::<Config>(input),
+            Ok(Config {
+                name: "api",
+                description: "line\nbreak".to_string(),
+                port: 8080,
+                ratio: 1.0,
+                debug: true,
+                timeout: Duration::from_secs(90),
+                cache: 64 << 20,
+                started: "2024-02-29T09:30:00Z".to_string(),
+                missing: None,
+                nothing: None,
+                servers: vec![
+                    Server {
+                        host: "a".to_string(),
+                        weight: Some(2),
+                    },
+                    Server {
+                        host: "b".to_string(),
+                        weight: None,
+                    },
+                ],
+                labels: vec![
+                    ("team".to_string(), "core".to_string()),
+                    ("tier".to_string(), "1".to_string()),
+                ]
+                .into_iter()
+                .collect(),
+                mode: Mode::Custom { min: 1, max: 5 },
+            })
+        );
+    }
+
+    #[test]
+    fn enums() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Modes {
+            modes: Vec<Mode>,
+        }
+
+        let input = indoc! {r#"
+            modes:
+              - "Off"
+              - Off: ~
+              - Fixed: 3
+              - Range: [1, 2]
+              - Custom:
+                  min: 1
+                  max: 2
+        "#};
+        assert_eq!(
+            from_str::<Modes>(input),
+            Ok(Modes {
+                modes: vec![
+                    Mode::Off,
+                    Mode::Off,
+                    Mode::Fixed(3),
+                    Mode::Range(1, 2),
+                    Mode::Custom { min: 1, max: 2 },
+                ],
+            })
+        );
+
+        let input = "modes:\n  - Fixed: 1\n    Off: ~\n";
+        assert_eq!(
+            from_str::<Modes>(input),
+            Err(Error::Deserialize {
+                message: "invalid type: map, expected a string or an object with a single key"
+                    .to_string(),
+                position: position(11, 2, 5),
+            })
+        );
+    }
+
+    #[test]
+    fn error_positions() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        #[serde(deny_unknown_fields)]
+        struct Outer {
+            inner: Inner,
+        }
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        #[serde(deny_unknown_fields)]
+        struct Inner {
+            port: u16,
+            #[serde(default)]
+            hosts: Vec<String>,
+        }
+
+        let error = |input| match from_str::<Outer>(input) {
+            Err(Error::Deserialize { message, position }) => (message, position),
+            result => panic!("expected an error, got {:?}", result),
+        };
+
+        assert_eq!(
+            error("inner:\n  port: 70000\n"),
+            (
+                "invalid value: integer `70000`, expected u16".to_string(),
+                position(15, 2, 9)
+            )
+        );
+        assert_eq!(
+            error("inner:\n  port: 1\n  hosts: [\"a\", 2]\n"),
+            (
+                "invalid type: integer `2`, expected a string".to_string(),
+                position(32, 3, 16)
+            )
+        );
+        // Missing fields point at the object they're missing from.
+        assert_eq!(
+            error("inner:\n  hosts: []\n"),
+            ("missing field `port`".to_string(), position(9, 2, 3))
+        );
+        // A dotted key's objects point at the key.
+        assert_eq!(
+            error("inner.hosts: []\n"),
+            ("missing field `port`".to_string(), position(0, 1, 1))
+        );
+        // Unknown fields point at their value.
+        assert_eq!(
+            error("inner:\n  port: 1\n  host: \"x\"\n").1,
+            position(25, 3, 9)
+        );
+        // Values with nothing after the key are where the value would be.
+        assert_eq!(error("inner:\n  port:\n").1, position(14, 2, 8));
+
+        // Parse errors are passed through.
+        assert_eq!(
+            from_str::<Outer>("inner: \"x"),
+            Err(Error::UnterminatedString(Position::new("inner: \"x", 7)))
+        );
+    }
+
+    #[test]
+    fn borrowed_strings() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Borrowed<'a> {
+            #[serde(borrow)]
+            names: Vec<&'a str>,
+            #[serde(borrow)]
+            by_key: BTreeMap<&'a str, &'a str>,
+        }
+
+        let input = "names: [\"a\", 'b\\c', \"d\"]\nby_key: { \"x-y\": 'z' }\n";
+        let borrowed = from_str::<Borrowed>(input).unwrap();
+        assert_eq!(borrowed.names, ["a", "b\\c", "d"]);
+        assert_eq!(borrowed.by_key.get("x-y"), Some(&"z"));
+
+        // Strings with escapes can't be borrowed.
+        assert_eq!(
+            from_str::<Borrowed>("names: [\"a\\n\"]\nby_key: {}\n"),
+            Err(Error::Deserialize {
+                message: "invalid type: string \"a\\n\", expected a borrowed string".to_string(),
+                position: position(8, 1, 9),
+            })
+        );
+    }
+
+    #[test]
+    fn sequences() {
+        assert_eq!(
+            from_str::<BTreeMap<String, (u8, u8)>>("a: [1, 2]\n"),
+            Ok(vec![("a".to_string(), (1, 2))].into_iter().collect())
+        );
+        assert_eq!(
+            from_str::<BTreeMap<String, (u8, u8)>>("a: [1, 2, 3]\n"),
+            Err(Error::Deserialize {
+                message: "invalid length 3, expected 2 items".to_string(),
+                position: position(3, 1, 4),
+            })
+        );
+    }
+
+    #[test]
+    fn value() {
+        let input = "a:\n  - 1\n  - \"x\"\n";
+        let value = parse(input).unwrap();
+
+        assert_eq!(
+            from_value::<BTreeMap<String, Vec<String>>>(&value),
+            Err(Error::Deserialize {
+                message: "invalid type: integer `1`, expected a string".to_string(),
+                position: None,
+            })
+        );
+        assert_eq!(
+            from_value::<BTreeMap<String, Vec<Option<u8>>>>(&parse("a: [1, ~]").unwrap()),
+            Ok(vec![("a".to_string(), vec![Some(1), None])]
+                .into_iter()
+                .collect())
+        );
+    }
+}
@@ -0,0 +1,514 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::{Error, Position};
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Renders an [`Error`] the way rustc does, with the offending line of the source, the error
+/// underlined, and a hint on how to fix it where there is one.
+///
+/// ```text
+/// error: expected `:` after key
+///  --> config.ooml:1:7
+///   |
+/// 1 | key_1 = 1
+///   |       ^ expected `:`
+///   |
+///   = help: did you mean `: ` after the key?
+/// ```
+#[derive(Debug, Clone)]
+pub struct Diagnostic<'a> {
+    error: &'a Error,
+    source: &'a str,
+    file_name: Option<&'a str>,
+    color: bool,
+}
+
+impl<'a> Diagnostic<'a> {
+    /// `source` must be the input that was parsed to produce `error`.
+    pub fn new(error: &'a Error, source: &'a str) -> Self {
+        Self {
+            error,
+            source,
+            file_name: None,
+            color: false,
+        }
+    }
+
+    /// The name of the file `source` was read from, shown before the line and column.
+    pub fn file_name(mut self, file_name: &'a str) -> Self {
+        self.file_name = Some(file_name);
+        self
+    }
+
+    /// Whether to use ANSI escape codes to color the output. Off by default.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// The error's message without the position or source line, like `expected a value`, for
+    /// reporting the error where the position is given separately.
+    pub fn message(&self) -> String {
+        let (message, label, _) = describe(self.error);
+        match self.error.position() {
+            Some(_) => message.to_string(),
+            None => format!("{}: {}", message, label),
+        }
+    }
+
+    fn paint(&self, style: &'static str, text: impl fmt::Display) -> String {
+        if self.color {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// The text of the line `position` is on, without its newline.
+    fn line(&self, position: Position) -> &'a str {
+        let start = self.source[..position.offset]
+            .rfind('\n')
+            .map_or(0, |i| i + 1);
+        let line = &self.source[start..];
+
+        line.split('\n').next().unwrap_or(line)
+    }
+}
+
+/// The message, underline label and help for each kind of error.
+fn describe(error: &Error) -> (&'static str, Cow<'static, str>, Option<&'static str>) {
+    match error {
+        Error::UnexpectedToken(_) => ("unexpected token", "unexpected".into(), None),
+        Error::ExpectedValue(_) => (
+            "expected a value",
+            "expected a value here".into(),
+            Some("a value is a string, number, boolean or null on the same line, or an indented block on the next line"),
+        ),
+        Error::MissingColon(_) => (
+            "expected `:` after key",
+            "expected `:`".into(),
+            Some("did you mean `: ` after the key?"),
+        ),
+        Error::InvalidEscape(_) => (
+            "invalid escape sequence",
+            "unknown escape".into(),
+            Some("the valid escapes are `\\\"`, `\\\\`, `\\n`, `\\t` and `\\u{...}`, or use a single quoted string where backslashes aren't escapes"),
+        ),
+        Error::UnterminatedString(_) => (
+            "unterminated string",
+            "string is never closed".into(),
+            Some("add a closing `\"` before the end of the line, or use a block string (`|`) for text spanning multiple lines"),
+        ),
+        Error::UnterminatedRawString(_) => (
+            "unterminated raw string",
+            "string is never closed".into(),
+            Some("add a closing `'` before the end of the line, or use a block string (`|`) for text spanning multiple lines"),
+        ),
+        Error::InvalidNumber(_, reason) => (
+            "invalid number",
+            reason.to_string().into(),
+            Some("if this isn't meant to be a number, put it in quotes to make it a string"),
+        ),
+        Error::InvalidByteSize(_, reason) => (
+            "invalid byte size",
+            reason.to_string().into(),
+            Some("byte sizes are numbers followed by `B`, a decimal unit like `KB` or `MB`, or a binary unit like `KiB` or `MiB`"),
+        ),
+        Error::InvalidDateTime(_) => (
+            "invalid date or time",
+            "not a valid date or time".into(),
+            Some("dates are `YYYY-MM-DD` and times are `HH:MM:SS` with an optional fraction, joined by a `T` with an optional `Z` or `+HH:MM` offset for a date-time"),
+        ),
+        Error::InvalidDuration(_) => (
+            "invalid duration",
+            "not a valid duration".into(),
+            Some("durations are numbers followed by `d`, `h`, `m`, `s`, `ms`, `us` or `ns`, largest unit first, like `1h30m`"),
+        ),
+        Error::UnclosedFlow(_) => (
+            "unclosed flow collection",
+            "never closed".into(),
+            Some("inline arrays and objects have to be closed on the line they start on, use the indented form for longer ones"),
+        ),
+        Error::BadIndent(_) => (
+            "bad indentation",
+            "unexpected indentation".into(),
+            Some("indent with spaces, and line up each entry with the others in its block"),
+        ),
+        Error::TooDeep(_) => ("collections nested too deeply", "nested too deeply".into(), None),
+        Error::DuplicateKey { .. } => (
+            "duplicate key",
+            "already defined in this object".into(),
+            Some("remove or rename one of the keys"),
+        ),
+        Error::ConflictingKey { .. } => (
+            "conflicting key",
+            "conflicts with an earlier definition".into(),
+            Some("a key can't be both an object and another kind of value"),
+        ),
+        Error::Deserialize { message, .. } => ("invalid value", message.clone().into(), None),
+        Error::Serialize(message) => ("can't serialize value", message.clone().into(), None),
+        Error::Edit(message) => ("can't edit document", message.clone().into(), None),
+    }
+}
+
+/// How many characters from the error's position to underline.
+fn underline_len(error: &Error, rest_of_line: &str) -> usize {
+    let len = match error {
+        Error::UnterminatedString(_) | Error::UnterminatedRawString(_) => {
+            rest_of_line.trim_end().chars().count()
+        }
+        Error::InvalidNumber(..)
+        | Error::InvalidByteSize(..)
+        | Error::Deserialize { .. }
+        | Error::InvalidDateTime(_)
+        | Error::InvalidDuration(_) => rest_of_line
+            .chars()
+            .take_while(|c| !c.is_whitespace() && *c != '#')
+            .count(),
+        Error::BadIndent(_) => rest_of_line
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .count(),
+        Error::DuplicateKey { key, .. } | Error::ConflictingKey { key, .. } => {
+            key_len(key, rest_of_line)
+        }
+        _ => 1,
+    };
+
+    len.max(1)
+}
+
+/// How many characters `key` takes up at the start of `rest_of_line`, as it's written there. Quoted
+/// keys include their quotes and escapes.
+fn key_len(key: &str, rest_of_line: &str) -> usize {
+    let mut chars = rest_of_line.chars();
+    let quote = match chars.next() {
+        Some(quote @ '"') | Some(quote @ '\'') => quote,
+        _ => return key.chars().count(),
+    };
+
+    let mut len = 1;
+    let mut escaped = false;
+    for c in chars {
+        len += 1;
+        match c {
+            c if c == quote && !escaped => break,
+            '\\' if quote == '"' && !escaped => escaped = true,
+            _ => escaped = false,
+        }
+    }
+    len
+}
+
+impl fmt::Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (message, label, help) = describe(self.error);
+        let position = match self.error.position() {
+            Some(position) => position,
+            // Nothing to point at.
+            None => {
+                return write!(
+                    f,
+                    "{}{}",
+                    self.paint(RED, "error"),
+                    self.paint(BOLD, format!(": {}: {}", message, label))
+                )
+            }
+        };
+
+        let line = self.line(position);
+        let (before, rest_of_line) = line.split_at(
+            line.char_indices()
+                .nth(position.column - 1)
+                .map_or(line.len(), |(i, _)| i),
+        );
+
+        // Keep tabs in the padding so the underline lines up with the source line.
+        let padding = before
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        let underline = "^".repeat(underline_len(self.error, rest_of_line));
+
+        let line_number = position.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+        let bar = self.paint(BLUE, "|");
+
+        writeln!(
+            f,
+            "{}{}",
+            self.paint(RED, "error"),
+            self.paint(BOLD, format!(": {}", message))
+        )?;
+        match self.file_name {
+            Some(file_name) => writeln!(
+                f,
+                "{}{} {}:{}",
+                gutter,
+                self.paint(BLUE, "-->"),
+                file_name,
+                position
+            )?,
+            None => writeln!(f, "{}{} {}", gutter, self.paint(BLUE, "-->"), position)?,
+        }
+        writeln!(f, "{} {}", gutter, bar)?;
+        writeln!(f, "{} {} {}", self.paint(BLUE, &line_number), bar, line)?;
+        write!(
+            f,
+            "{} {} {}{}",
+            gutter,
+            bar,
+            padding,
+            self.paint(RED, format!("{} {}", underline, label))
+        )?;
+
+        if let Some(help) = help {
+            writeln!(f)?;
+            writeln!(f, "{} {}", gutter, bar)?;
+            write!(
+                f,
+                "{} {} {}: {}",
+                gutter,
+                self.paint(BLUE, "="),
+                self.paint(BOLD, "help"),
+                help
+            )?;
+        }
+
+        if let Error::DuplicateKey { first, .. } | Error::ConflictingKey { first, .. } = self.error
+        {
+            writeln!(f)?;
+            write!(
+                f,
+                "{} {} {}: first defined at {}",
+                gutter,
+                self.paint(BLUE, "="),
+                self.paint(BOLD, "note"),
+                first
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, NumberError};
+
+    fn render(source: &str) -> String {
+        let error = parse(source).unwrap_err();
+        Diagnostic::new(&error, source).to_string()
+    }
+
+    #[test]
+    fn unterminated_string() {
+        let source = "key_1: 1\nkey_2: \"foo  \n";
+
+        assert_eq!(
+            render(source),
+            [
+                "error: unterminated string",
+                " --> 2:8",
+                "  |",
+                "2 | key_2: \"foo  ",
+                "  |        ^^^^ string is never closed",
+                "  |",
+                "  = help: add a closing `\"` before the end of the line, or use a block string (`|`) for text spanning multiple lines",
+            ]
+            .join("\n")
+        );
+
+        let error = parse(source).unwrap_err();
+        assert_eq!(
+            Diagnostic::new(&error, source).message(),
+            "unterminated string"
+        );
+    }
+
+    #[test]
+    fn missing_colon_with_file_name() {
+        let source = "key_1 = 1";
+        let error = parse(source).unwrap_err();
+
+        assert_eq!(
+            Diagnostic::new(&error, source)
+                .file_name("config.ooml")
+                .to_string(),
+            [
+                "error: expected `:` after key",
+                " --> config.ooml:1:7",
+                "  |",
+                "1 | key_1 = 1",
+                "  |       ^ expected `:`",
+                "  |",
+                "  = help: did you mean `: ` after the key?",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn bad_indent() {
+        let source = "a:\n    b: 1\n  c: 2\n";
+
+        assert_eq!(
+            render(source),
+            [
+                "error: bad indentation",
+                " --> 3:1",
+                "  |",
+                "3 |   c: 2",
+                "  | ^^ unexpected indentation",
+                "  |",
+                "  = help: indent with spaces, and line up each entry with the others in its block",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn multi_byte_and_tabs() {
+        let error = Error::InvalidNumber(
+            Position {
+                offset: 7,
+                line: 1,
+                column: 7,
+            },
+            NumberError::MultipleDecimalPoints,
+        );
+        assert_eq!(
+            Diagnostic::new(&error, "é: 3.1.4")
+                .to_string()
+                .lines()
+                .nth(4),
+            Some("  |       ^^ numbers can only have one `.`")
+        );
+
+        let source = "é\t: \"\\q\"";
+        let error = Error::InvalidEscape(Position {
+            offset: 6,
+            line: 1,
+            column: 6,
+        });
+        assert_eq!(
+            Diagnostic::new(&error, source).to_string().lines().nth(4),
+            Some("  |  \t   ^ unknown escape")
+        );
+    }
+
+    #[test]
+    fn end_of_input() {
+        let source = "key_1:\n";
+        let error = Error::ExpectedValue(Position::new(source, source.len()));
+
+        assert_eq!(
+            Diagnostic::new(&error, source)
+                .to_string()
+                .lines()
+                .take(5)
+                .collect::<Vec<_>>(),
+            [
+                "error: expected a value",
+                " --> 2:1",
+                "  |",
+                "2 | ",
+                "  | ^ expected a value here",
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_key() {
+        let source = "a: 1\nb:\n  c: 2\na: 3\n";
+
+        assert_eq!(
+            render(source),
+            [
+                "error: duplicate key",
+                " --> 4:1",
+                "  |",
+                "4 | a: 3",
+                "  | ^ already defined in this object",
+                "  |",
+                "  = help: remove or rename one of the keys",
+                "  = note: first defined at 1:1",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn duplicate_quoted_key() {
+        for (source, underline) in &[
+            ("\"a b\": 1\n\"a b\": 2\n", "^^^^^"),
+            ("'a b': 1\n\"a b\": 2\n", "^^^^^"),
+            ("a: 1\n\"\\u{61}\": 2\n", "^^^^^^^^"),
+            ("\"a\\\"b\": 1\n'a\"b': 2\n", "^^^^^"),
+            ("\"a\\\"b\": 1\n\"a\\\"b\": 2\n", "^^^^^^"),
+        ] {
+            let rendered = render(source);
+            let line = rendered.lines().nth(4).unwrap();
+            assert_eq!(
+                line,
+                format!("  | {} already defined in this object", underline),
+                "{}",
+                rendered
+            );
+        }
+    }
+
+    #[test]
+    fn conflicting_key() {
+        let source = "server:\n  port: 80\nserver.port.tls: 443\n";
+
+        assert_eq!(
+            render(source),
+            [
+                "error: conflicting key",
+                " --> 3:1",
+                "  |",
+                "3 | server.port.tls: 443",
+                "  | ^^^^^^^^^^^ conflicts with an earlier definition",
+                "  |",
+                "  = help: a key can't be both an object and another kind of value",
+                "  = note: first defined at 2:3",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn without_position() {
+        let error = Error::Deserialize {
+            message: "missing field `port`".to_string(),
+            position: None,
+        };
+
+        assert_eq!(
+            Diagnostic::new(&error, "").to_string(),
+            "error: invalid value: missing field `port`"
+        );
+        assert_eq!(error.to_string(), "missing field `port`");
+        assert_eq!(
+            Diagnostic::new(&error, "").message(),
+            "invalid value: missing field `port`"
+        );
+    }
+
+    #[test]
+    fn color() {
+        let source = "key_1 = 1";
+        let error = parse(source).unwrap_err();
+        let rendered = Diagnostic::new(&error, source).color(true).to_string();
+
+        assert!(rendered.starts_with("\x1b[1;31merror\x1b[0m\x1b[1m: expected `:` after key"));
+        assert!(rendered.contains("\x1b[1;31m^ expected `:`\x1b[0m"));
+    }
+}
@@ -0,0 +1,1203 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Range;
+
+use crate::{emit, parse_with_syntax, Error, Map, Path, PathSegment, Value};
+
+/// A document that can be edited without changing how the rest of it is written. Edits only
+/// rewrite the text of the entries they're made to, so comments, blank lines, key order and
+/// spacing everywhere else come back byte for byte.
+///
+/// New values are written the way [`emit`](crate::emit) writes them, with nested blocks indented
+/// by 2 past their key unless they replace a block that was indented differently.
+#[derive(Debug, Clone)]
+pub struct Document {
+    text: String,
+    value: Value<'static>,
+    /// Each entry and array item, in the order they start.
+    nodes: Vec<Node>,
+}
+
+/// Where an entry or array item is in the text, as byte offsets.
+#[derive(Debug, Clone)]
+pub(crate) struct Node {
+    /// The path of the value. For dotted keys this is nested in the objects they create.
+    pub(crate) path: Path<'static>,
+    /// How many segments of the path are of the collection the entry is written in.
+    pub(crate) depth: usize,
+    /// Whether the entry is in a flow collection.
+    pub(crate) flow: bool,
+    /// The start of the key, or of the `-` or value of an array item.
+    pub(crate) start: usize,
+    /// After the `:` or `-`, or at the value of a flow array item.
+    pub(crate) slot: usize,
+    /// The start of the value, which is `slot` when there's no value.
+    pub(crate) value: usize,
+    /// The end of the value, for values that aren't block collections.
+    pub(crate) value_end: Option<usize>,
+}
+
+/// How the value of an entry in a block collection is written.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Kind {
+    /// Nothing on the line or nested under it, which is null.
+    Empty,
+    /// On the line of the key or `-`. Block strings continue on the lines after it.
+    Inline,
+    /// A block collection starting on a later line.
+    Nested,
+    /// A block collection starting on the line of the `-`, like `- name: a`.
+    Compact,
+}
+
+impl Document {
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        let (value, nodes) = parse_with_syntax(input)?;
+        let offset = |rest: &str| input.len() - rest.len();
+
+        let nodes = nodes
+            .into_iter()
+            .map(|node| Node {
+                path: node.path.iter().map(owned_segment).collect(),
+                depth: node.depth,
+                flow: node.flow,
+                start: offset(node.start),
+                slot: offset(node.slot),
+                value: offset(node.value),
+                value_end: node.value_end.map(offset),
+            })
+            .collect();
+
+        Ok(Self {
+            text: input.to_string(),
+            value: owned(value),
+            nodes,
+        })
+    }
+
+    pub(crate) fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// The value of the whole document.
+    pub fn value(&self) -> &Value<'_> {
+        &self.value
+    }
+
+    /// The value at `path`, if there is one.
+    pub fn get(&self, path: &[PathSegment<'_>]) -> Option<&Value<'_>> {
+        let mut value = &self.value;
+        for segment in path {
+            value = match (value, segment) {
+                (Value::Object(object), PathSegment::Key(key)) => object.get(key.as_ref())?,
+                (Value::Array(array), PathSegment::Index(index)) => array.get(*index)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+
+    /// Sets the value at `path`, replacing the text of the value that's there, or adding an entry
+    /// after the last one in its object if there isn't one. Objects along the path that don't
+    /// exist are created, and an index one past the end of an array adds an item to it. Setting
+    /// the empty path replaces the whole document.
+    pub fn set(&mut self, path: &[PathSegment<'_>], value: &Value<'_>) -> Result<(), Error> {
+        let definitions = self.definitions(path);
+        let (first, others) = match definitions.split_first() {
+            Some((first, others)) => (*first, others),
+            None => return self.add(path, value),
+        };
+
+        // Values defined in several places, like `a:` and `a.b:`, are replaced where they're
+        // first defined.
+        let mut splices = Vec::new();
+        for &other in others {
+            splices.push(self.removal(other)?);
+        }
+
+        if self.nodes[first].path.len() == path.len() {
+            splices.extend(self.replacement(first, value)?);
+        } else {
+            // A dotted key going through `path`, which is replaced by an entry for `path` itself.
+            let node = &self.nodes[first];
+            let key = self.relative_key(node.depth, path);
+            let range = match node.value_end {
+                Some(end) if node.flow => node.start..end,
+                _ => node.start..self.end(first),
+            };
+            let text = if node.flow {
+                format!("{}: {}", key, emit::flow(value)?)
+            } else {
+                entry(self.column(node.start), Some(&key), value)?
+            };
+            splices.push((range, text));
+        }
+
+        self.edit(splices, Some((path, value)))
+    }
+
+    /// Removes the entry or array item at `path`, along with the comments on the lines right
+    /// before it. Removing the only entry of a nested collection leaves it empty, like `{}`, and
+    /// removing the only entry of the document leaves it empty, which it can't be as an array.
+    pub fn remove(&mut self, path: &[PathSegment<'_>]) -> Result<(), Error> {
+        let definitions = self.definitions(path);
+        let parent = match path.split_last() {
+            Some((_, parent)) if !definitions.is_empty() => parent,
+            _ => return Err(not_found(path)),
+        };
+
+        let empty = match self.get(parent) {
+            Some(Value::Object(object)) if object.len() == 1 => Some(Value::Object(Map::new())),
+            Some(Value::Array(array)) if array.len() == 1 => Some(Value::Array(Vec::new())),
+            _ => None,
+        };
+        match empty {
+            Some(Value::Array(_)) if parent.is_empty() => return Err(empty_document(path)),
+            Some(empty) if !parent.is_empty() => return self.set(parent, &empty),
+            _ => {}
+        }
+
+        let mut splices = Vec::new();
+        for index in definitions {
+            if self.next_sibling(index).is_some() || self.previous_sibling(index).is_some() {
+                splices.push(self.removal(index)?);
+                continue;
+            }
+
+            // The only entry of its collection as it's written, though not of the object it's
+            // in, when dotted keys add to the object elsewhere.
+            let node = &self.nodes[index];
+            let empty = match node.path.last() {
+                Some(PathSegment::Index(_)) => Value::Array(Vec::new()),
+                _ => Value::Object(Map::new()),
+            };
+            match self.nodes[..index]
+                .iter()
+                .rposition(|other| other.depth < node.depth)
+            {
+                Some(parent) => splices.extend(self.replacement(parent, &empty)?),
+                None if matches!(empty, Value::Object(_)) => splices.push(self.removal(index)?),
+                None => return Err(empty_document(path)),
+            }
+        }
+        self.edit(splices, None)
+    }
+
+    /// Adds `value` right after the entry or array item at `path`, in the same collection.
+    /// `segment` is where it's added, a key that isn't in the object yet or the index after
+    /// `path`'s in an array.
+    pub fn insert_after(
+        &mut self,
+        path: &[PathSegment<'_>],
+        segment: PathSegment<'_>,
+        value: &Value<'_>,
+    ) -> Result<(), Error> {
+        let index = match self.definitions(path).last() {
+            Some(&index) => index,
+            None => return Err(not_found(path)),
+        };
+        let (last, parent) = path.split_last().ok_or_else(|| not_found(path))?;
+
+        let valid = match (self.get(parent), last, &segment) {
+            (Some(Value::Object(object)), _, PathSegment::Key(key)) => {
+                !object.contains_key(key.as_ref())
+            }
+            (Some(Value::Array(_)), PathSegment::Index(last), PathSegment::Index(index)) => {
+                *index == last + 1
+            }
+            _ => false,
+        };
+        if !valid {
+            let mut new = parent.to_vec();
+            new.push(segment);
+            return Err(Error::Edit(format!(
+                "can't insert `{}` after `{}`",
+                describe(&new),
+                describe(path)
+            )));
+        }
+
+        let mut new = parent.to_vec();
+        new.push(segment);
+        let key = match new.last() {
+            Some(PathSegment::Key(_)) => Some(self.relative_key(self.nodes[index].depth, &new)),
+            _ => None,
+        };
+
+        let node = &self.nodes[index];
+        let splice = match (node.flow, node.value_end) {
+            (true, Some(end)) => {
+                let text = match key {
+                    Some(key) => format!(", {}: {}", key, emit::flow(value)?),
+                    None => format!(", {}", emit::flow(value)?),
+                };
+                (end..end, text)
+            }
+            _ => {
+                // After the comments under the entry, which a block string would take as lines.
+                let column = self.column(node.start);
+                let at = self.comments_under(self.end(index), column);
+                let text = format!(
+                    "{}{}{}",
+                    self.line_break_at(at),
+                    " ".repeat(column),
+                    entry(column, key.as_deref(), value)?
+                );
+                (at..at, text)
+            }
+        };
+
+        self.edit(vec![splice], Some((&new, value)))
+    }
+
+    /// Adds the value at `path`, which isn't in the document.
+    fn add(&mut self, path: &[PathSegment<'_>], value: &Value<'_>) -> Result<(), Error> {
+        let (last, parent) = match path.split_last() {
+            Some(split) => split,
+            // An empty document's comments are kept before the new value.
+            None if self.nodes.is_empty() => {
+                let mut text = self.text.clone();
+                if !text.is_empty() && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                text.push_str(&emit::emit(value)?);
+                return self.edit_text(text, Some((path, value)));
+            }
+            None => return self.edit_text(emit::emit(value)?, Some((path, value))),
+        };
+
+        // The entry to add the value after, or the value to set the parent to when there isn't
+        // one to add it after.
+        let mut sibling = parent.to_vec();
+        let parent_value = match (self.get(parent), last) {
+            (Some(Value::Object(object)), PathSegment::Key(_)) if !object.is_empty() => {
+                let (key, _) = object.last().ok_or_else(|| not_found(path))?;
+                sibling.push(PathSegment::Key(Cow::Owned(key.to_string())));
+                None
+            }
+            (Some(Value::Array(array)), PathSegment::Index(index))
+                if !array.is_empty() && *index == array.len() =>
+            {
+                sibling.push(PathSegment::Index(index - 1));
+                None
+            }
+            (None, PathSegment::Key(key))
+            | (Some(Value::Null), PathSegment::Key(key))
+            | (Some(Value::Object(_)), PathSegment::Key(key)) => {
+                let mut object = Map::new();
+                object.insert(Cow::Owned(key.to_string()), owned(value.clone()));
+                Some(Value::Object(object))
+            }
+            (None, PathSegment::Index(0))
+            | (Some(Value::Null), PathSegment::Index(0))
+            | (Some(Value::Array(_)), PathSegment::Index(0)) => {
+                Some(Value::Array(vec![owned(value.clone())]))
+            }
+            _ => return Err(not_found(path)),
+        };
+
+        match parent_value {
+            Some(parent_value) => self.set(parent, &parent_value),
+            None => self.insert_after(&sibling, last.clone(), value),
+        }
+    }
+
+    /// The entries defining the value at `path`. There's more than one when dotted keys add to
+    /// an object defined elsewhere, and for objects only defined by dotted keys these are the
+    /// dotted keys going through it.
+    fn definitions(&self, path: &[PathSegment<'_>]) -> Vec<usize> {
+        (0..self.nodes.len())
+            .filter(|&i| {
+                let node = &self.nodes[i];
+                node.depth < path.len() && starts_with(&node.path, path)
+            })
+            .collect()
+    }
+
+    /// The edits replacing the value of the entry `index` with `value`.
+    fn replacement(
+        &self,
+        index: usize,
+        value: &Value<'_>,
+    ) -> Result<Vec<(Range<usize>, String)>, Error> {
+        let node = &self.nodes[index];
+        let item = matches!(node.path.last(), Some(PathSegment::Index(_)));
+        let column = self.column(node.start);
+        let nested = column + 2;
+
+        if node.flow {
+            let end = node.value_end.unwrap_or(node.value);
+            return Ok(vec![(node.value..end, emit::flow(value)?)]);
+        }
+
+        // Block strings can only be used when no comments under the entry follow the lines being
+        // replaced, which end at `after`, as they would be read as lines of the string.
+        let block_indent =
+            |after: usize| Some(nested).filter(|_| self.comments_under(after, column) == after);
+        // The text of the value when it starts at `used`, or `None` if it's a block collection.
+        // Block strings can also only be used when nothing follows `end` on its line.
+        let inline = |used: usize, end: usize, after: usize| {
+            let block_indent = block_indent(after).filter(|_| self.rest_of_line(end).is_empty());
+            emit::inline(value, used, block_indent)
+        };
+
+        let splices = match self.kind(index) {
+            Kind::Inline => {
+                let end = node.value_end.unwrap_or(node.value);
+                let flow = self.text[node.value..].starts_with(&['[', '{'][..])
+                    && matches!(value, Value::Object(_) | Value::Array(_));
+
+                match inline(self.column(node.value), end, self.line_end(end))? {
+                    // Flow collections stay flow collections.
+                    _ if flow => vec![(node.value..end, emit::flow(value)?)],
+                    Some(text) => vec![(node.value..end, text)],
+                    None if item => {
+                        vec![(node.value..end, compact(value, self.column(node.value))?)]
+                    }
+                    None => {
+                        let at = self.comments_under(self.line_end(end), column);
+                        vec![
+                            (
+                                at..at,
+                                self.line_break_at(at) + &emit::block(value, nested)?,
+                            ),
+                            (node.slot..end, String::new()),
+                        ]
+                    }
+                }
+            }
+            // The value goes after a space, which may be past the end of the text.
+            Kind::Empty => match inline(
+                self.column(node.slot) + 1,
+                node.slot,
+                self.line_end(node.slot),
+            )? {
+                Some(text) => vec![(node.slot..node.slot, format!(" {}", text))],
+                None if item => vec![(
+                    node.slot..node.slot,
+                    format!(" {}", compact(value, self.column(node.slot) + 1)?),
+                )],
+                None => {
+                    let at = self.comments_under(self.line_end(node.slot), column);
+                    vec![(
+                        at..at,
+                        self.line_break_at(at) + &emit::block(value, nested)?,
+                    )]
+                }
+            },
+            Kind::Nested => {
+                let end = self.end(index);
+                match inline(self.column(node.slot) + 1, node.slot, end)? {
+                    Some(text) => vec![
+                        (self.line_end(node.slot)..end, String::new()),
+                        (node.slot..node.slot, format!(" {}", text)),
+                    ],
+                    None => {
+                        let indent = self.column(node.value);
+                        vec![(
+                            self.line_start(node.value)..end,
+                            emit::block(value, indent)?,
+                        )]
+                    }
+                }
+            }
+            Kind::Compact => {
+                let end = self.end(index);
+                let text = match emit::inline(value, self.column(node.value), block_indent(end))? {
+                    Some(text) => text + "\n",
+                    None => compact(value, self.column(node.value))? + "\n",
+                };
+                vec![(node.value..end, text)]
+            }
+        };
+
+        Ok(splices)
+    }
+
+    /// The edit removing the entry `index`.
+    fn removal(&self, index: usize) -> Result<(Range<usize>, String), Error> {
+        let node = &self.nodes[index];
+        let next = self.next_sibling(index);
+
+        if node.flow {
+            let end = node.value_end.unwrap_or(node.value);
+            let range = match (next, self.previous_sibling(index)) {
+                // The entry and the `,` after it.
+                (Some(next), _) => node.start..self.nodes[next].start,
+                // The `,` before the entry and the entry.
+                (None, Some(previous)) => self.nodes[previous].value_end.unwrap_or(node.start)..end,
+                (None, None) => return Err(not_found(&node.path)),
+            };
+            return Ok((range, String::new()));
+        }
+
+        let line_start = self.line_start(node.start);
+        let range = if self.text[line_start..node.start]
+            .trim_start_matches(' ')
+            .is_empty()
+        {
+            self.leading_comments(line_start)..self.end(index)
+        } else {
+            // The first entry of a compact collection, which the next entry takes the place of.
+            match next {
+                Some(next) => node.start..self.nodes[next].start,
+                None => return Err(not_found(&node.path)),
+            }
+        };
+        Ok((range, String::new()))
+    }
+
+    pub(crate) fn kind(&self, index: usize) -> Kind {
+        let node = &self.nodes[index];
+        match node.value_end {
+            Some(_) => Kind::Inline,
+            None if node.value == node.slot => Kind::Empty,
+            None if node.value < self.line_end(node.slot) => Kind::Compact,
+            None => Kind::Nested,
+        }
+    }
+
+    /// The end of the last line of a block entry, after its line break. Blank lines and comments
+    /// after it aren't part of it, as they lead up to whatever comes next.
+    fn end(&self, index: usize) -> usize {
+        let depth = self.nodes[index].depth;
+        let nested = self.nodes[index + 1..]
+            .iter()
+            .take_while(|node| node.depth > depth)
+            .count();
+
+        // Flow collections are on the line of the entry they're in.
+        let last = self.nodes[index..=index + nested]
+            .iter()
+            .rev()
+            .find(|node| !node.flow)
+            .unwrap_or(&self.nodes[index]);
+
+        self.line_end(last.value_end.unwrap_or(last.slot))
+    }
+
+    /// The next entry in the same collection as `index`.
+    pub(crate) fn next_sibling(&self, index: usize) -> Option<usize> {
+        let node = &self.nodes[index];
+        let next = index
+            + 1
+            + self.nodes[index + 1..]
+                .iter()
+                .take_while(|other| other.depth > node.depth)
+                .count();
+
+        self.nodes
+            .get(next)
+            .filter(|other| self.siblings(node, other))
+            .map(|_| next)
+    }
+
+    fn previous_sibling(&self, index: usize) -> Option<usize> {
+        let node = &self.nodes[index];
+        let previous = self.nodes[..index]
+            .iter()
+            .rposition(|other| other.depth <= node.depth)?;
+
+        Some(previous).filter(|&previous| self.siblings(node, &self.nodes[previous]))
+    }
+
+    fn siblings(&self, node: &Node, other: &Node) -> bool {
+        other.depth == node.depth
+            && other.flow == node.flow
+            && other.path[..other.depth] == node.path[..node.depth]
+    }
+
+    /// The start of the comments on the lines right before `line_start`, indented the same as
+    /// that line.
+    fn leading_comments(&self, line_start: usize) -> usize {
+        let indent = self.column_of_content(line_start);
+        let mut start = line_start;
+
+        while start > 0 {
+            let previous = self.line_start(start - 1);
+            let line = &self.text[previous..start];
+            if self.column_of_content(previous) != indent || !line.trim_start().starts_with('#') {
+                break;
+            }
+            start = previous;
+        }
+
+        start
+    }
+
+    /// The end of the comments from `offset` on that are indented past `column`, and so are under
+    /// the entry before them, or `offset` if there aren't any.
+    fn comments_under(&self, offset: usize, column: usize) -> usize {
+        let mut end = offset;
+        let mut start = offset;
+
+        while start < self.text.len() {
+            let next = self.line_end(start);
+            let line = self.text[start..next].trim();
+            if line.starts_with('#') && self.column_of_content(start) > column {
+                end = next;
+            } else if !line.is_empty() {
+                break;
+            }
+            start = next;
+        }
+
+        end
+    }
+
+    /// The key of an entry for `path`, written in the collection at `depth` of it, which is
+    /// dotted if the entry is nested more than one level in that collection.
+    fn relative_key(&self, depth: usize, path: &[PathSegment<'_>]) -> String {
+        path[depth..]
+            .iter()
+            .map(|segment| match segment {
+                PathSegment::Key(key) => emit::key(key),
+                PathSegment::Index(index) => index.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    pub(crate) fn line_start(&self, offset: usize) -> usize {
+        self.text[..offset].rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    /// The offset after the line break ending the line `offset` is on, or the end of the text.
+    pub(crate) fn line_end(&self, offset: usize) -> usize {
+        self.text[offset..]
+            .find('\n')
+            .map_or(self.text.len(), |i| offset + i + 1)
+    }
+
+    /// What follows `offset` on its line, excluding the line break.
+    pub(crate) fn rest_of_line(&self, offset: usize) -> &str {
+        self.text[offset..self.line_end(offset)].trim_end_matches('\n')
+    }
+
+    /// How many characters come before `offset` on its line.
+    pub(crate) fn column(&self, offset: usize) -> usize {
+        self.text[self.line_start(offset)..offset].chars().count()
+    }
+
+    /// The indentation of the line starting at `line_start`.
+    fn column_of_content(&self, line_start: usize) -> usize {
+        self.text[line_start..].len() - self.text[line_start..].trim_start_matches(' ').len()
+    }
+
+    /// A line break to put before lines added at `offset`, when it's at the end of a last line
+    /// without one.
+    fn line_break_at(&self, offset: usize) -> String {
+        if offset == self.text.len() && !self.text.is_empty() && !self.text.ends_with('\n') {
+            "\n".to_string()
+        } else {
+            String::new()
+        }
+    }
+
+    /// Makes the edits, which replace the ranges they're for and mustn't overlap, and parses the
+    /// result. `set` is the path and value the edits are setting, if they are.
+    fn edit(
+        &mut self,
+        mut splices: Vec<(Range<usize>, String)>,
+        set: Option<(&[PathSegment<'_>], &Value<'_>)>,
+    ) -> Result<(), Error> {
+        splices.sort_by_key(|(range, _)| std::cmp::Reverse((range.start, range.end)));
+
+        let mut text = self.text.clone();
+        for (range, with) in splices {
+            text.replace_range(range, &with);
+        }
+        self.edit_text(text, set)
+    }
+
+    fn edit_text(
+        &mut self,
+        text: String,
+        set: Option<(&[PathSegment<'_>], &Value<'_>)>,
+    ) -> Result<(), Error> {
+        // Edits are always valid and set what they're meant to, but this keeps the document
+        // usable if one doesn't.
+        let document = Document::parse(&text)
+            .map_err(|e| Error::Edit(format!("the edit would make the document invalid: {}", e)))?;
+        if let Some((path, value)) = set {
+            if document.get(path) != Some(value) {
+                return Err(Error::Edit(format!(
+                    "the edit would read back as a different value at `{}`",
+                    describe(path)
+                )));
+            }
+        }
+
+        *self = document;
+        Ok(())
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// The text of an entry or array item in a block, starting at its key or `-`, which is at
+/// `column`. Array items are written without a key.
+fn entry(column: usize, key: Option<&str>, value: &Value<'_>) -> Result<String, Error> {
+    let nested = column + 2;
+
+    let text = match key {
+        Some(key) => {
+            let used = column + key.chars().count() + 2;
+            match emit::inline(value, used, Some(nested))? {
+                Some(text) => format!("{}: {}\n", key, text),
+                None => format!("{}:\n{}", key, emit::block(value, nested)?),
+            }
+        }
+        None => match emit::inline(value, nested, Some(nested))? {
+            Some(text) => format!("- {}\n", text),
+            None => format!("- {}\n", compact(value, nested)?),
+        },
+    };
+
+    Ok(text)
+}
+
+/// A block collection starting at `column` of a line that already has something before it, like
+/// the value of an array item. It doesn't end with a line break.
+fn compact(value: &Value<'_>, column: usize) -> Result<String, Error> {
+    let block = emit::block(value, column)?;
+    Ok(block[column..].trim_end_matches('\n').to_string())
+}
+
+fn starts_with(path: &[PathSegment<'_>], prefix: &[PathSegment<'_>]) -> bool {
+    path.len() >= prefix.len() && path.iter().zip(prefix).all(|(a, b)| a == b)
+}
+
+/// A path for error messages, like `servers[0].name`.
+fn describe(path: &[PathSegment<'_>]) -> String {
+    let mut text = String::new();
+    for segment in path {
+        match segment {
+            PathSegment::Key(key) => {
+                if !text.is_empty() {
+                    text.push('.');
+                }
+                text.push_str(&emit::key(key));
+            }
+            PathSegment::Index(index) => text.push_str(&format!("[{}]", index)),
+        }
+    }
+    text
+}
+
+fn not_found(path: &[PathSegment<'_>]) -> Error {
+    Error::Edit(format!("there's no value at `{}`", describe(path)))
+}
+
+fn empty_document(path: &[PathSegment<'_>]) -> Error {
+    Error::Edit(format!(
+        "can't remove `{}`, as the document can't be an empty array",
+        describe(path)
+    ))
+}
+
+fn owned_segment(segment: &PathSegment<'_>) -> PathSegment<'static> {
+    match segment {
+        PathSegment::Key(key) => PathSegment::Key(Cow::Owned(key.to_string())),
+        PathSegment::Index(index) => PathSegment::Index(*index),
+    }
+}
+
+fn owned(value: Value<'_>) -> Value<'static> {
+    match value {
+        Value::Null => Value::Null,
+        Value::String(string) => Value::String(Cow::Owned(string.into_owned())),
+        Value::Integer(integer) => Value::Integer(integer),
+        Value::Float(float) => Value::Float(float),
+        Value::Bool(bool) => Value::Bool(bool),
+        Value::DateTime(date_time) => Value::DateTime(date_time),
+        Value::Date(date) => Value::Date(date),
+        Value::Time(time) => Value::Time(time),
+        Value::Duration(duration) => Value::Duration(duration),
+        Value::Bytes(bytes) => Value::Bytes(bytes),
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .map(|(key, value)| (Cow::Owned(key.into_owned()), owned(value)))
+                .collect(),
+        ),
+        Value::Array(array) => Value::Array(array.into_iter().map(owned).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use indoc::indoc;
+
+    /// A path like `servers.0.name`, where numbers are array indexes.
+    fn path(path: &str) -> Vec<PathSegment<'static>> {
+        path.split('.')
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| match segment.parse() {
+                Ok(index) => PathSegment::Index(index),
+                Err(_) => PathSegment::Key(Cow::Owned(segment.to_string())),
+            })
+            .collect()
+    }
+
+    fn string(string: &str) -> Value<'static> {
+        Value::String(Cow::Owned(string.to_string()))
+    }
+
+    fn object(entries: &[(&str, Value<'static>)]) -> Value<'static> {
+        Value::Object(
+            entries
+                .iter()
+                .map(|(key, value)| (Cow::Owned(key.to_string()), value.clone()))
+                .collect(),
+        )
+    }
+
+    fn set(input: &str, at: &str, value: Value<'_>) -> String {
+        let mut document = Document::parse(input).unwrap();
+        document.set(&path(at), &value).unwrap();
+        document.to_string()
+    }
+
+    fn remove(input: &str, at: &str) -> String {
+        let mut document = Document::parse(input).unwrap();
+        document.remove(&path(at)).unwrap();
+        document.to_string()
+    }
+
+    const DEPLOYMENT: &str = indoc! {r#"
+        # Deployment for the app.
+        image:
+          name:   "app"    # pinned
+          tag: "1.0" # bump me
+
+        replicas:  3
+        ports: [80,443]
+        env:
+            - name: "LOG"
+              value: "debug"
+            - name: "MODE"
+              value: 'prod'
+        # The end.
+    "#};
+
+    #[test]
+    fn untouched_text_is_kept() {
+        let edited = set(DEPLOYMENT, "image.tag", string("1.1"));
+        assert_eq!(
+            edited,
+            DEPLOYMENT.replace(r#"tag: "1.0" # bump me"#, r#"tag: "1.1" # bump me"#)
+        );
+        assert_eq!(Document::parse(DEPLOYMENT).unwrap().as_str(), DEPLOYMENT);
+    }
+
+    #[test]
+    fn set_inline_values() {
+        let cases = [
+            ("replicas", Value::Integer(5), "replicas:  5\n"),
+            ("ports.1", Value::Integer(8443), "ports: [80,8443]\n"),
+            ("ports", Value::Array(vec![]), "ports: []\n"),
+            ("env.1.value", string("it's"), "      value: \"it's\"\n"),
+            (
+                "image",
+                string("app:1.1"),
+                "image: \"app:1.1\"\n\nreplicas:",
+            ),
+        ];
+
+        for (at, value, expected) in cases.iter().cloned() {
+            let edited = set(DEPLOYMENT, at, value);
+            assert!(edited.contains(expected), "{} in {:?}", expected, edited);
+        }
+    }
+
+    #[test]
+    fn set_block_values() {
+        let limits = object(&[
+            ("cpu", Value::Integer(1)),
+            ("memory", Value::Bytes(1 << 30)),
+        ]);
+
+        assert_eq!(
+            set("a: 1 # one\nb: 2", "a", limits.clone()),
+            "a: # one\n  cpu: 1\n  memory: 1GiB\nb: 2"
+        );
+        assert_eq!(
+            set("a:\nb: 2\n", "a", limits.clone()),
+            "a:\n  cpu: 1\n  memory: 1GiB\nb: 2\n"
+        );
+        assert_eq!(
+            set("a: 1", "a", limits.clone()),
+            "a:\n  cpu: 1\n  memory: 1GiB\n"
+        );
+        // Blocks that are replaced keep their indentation.
+        assert_eq!(
+            set("a:\n    b: 1\n    c: 2\nd: 3\n", "a", limits.clone()),
+            "a:\n    cpu: 1\n    memory: 1GiB\nd: 3\n"
+        );
+        assert_eq!(
+            set("a:\n    b: 1\n\nd: 3\n", "a", Value::Null),
+            "a: null\n\nd: 3\n"
+        );
+
+        // Array items are written compactly.
+        assert_eq!(
+            set("- 1 # one\n- 2\n", "0", limits.clone()),
+            "- cpu: 1\n  memory: 1GiB # one\n- 2\n"
+        );
+        assert_eq!(
+            set("-\n- 2\n", "0", limits.clone()),
+            "- cpu: 1\n  memory: 1GiB\n- 2\n"
+        );
+        assert_eq!(
+            set("- a: 1\n  b: 2\n- 3\n", "0", limits.clone()),
+            "- cpu: 1\n  memory: 1GiB\n- 3\n"
+        );
+        assert_eq!(
+            set("- a: 1\n  b: 2\n- 3\n", "0", Value::Bool(true)),
+            "- true\n- 3\n"
+        );
+
+        // In flow collections everything is written in the flow style.
+        assert_eq!(
+            set("a: { b: 1, c: 2 }\n", "a.b", limits),
+            "a: { b: { cpu: 1, memory: 1GiB }, c: 2 }\n"
+        );
+    }
+
+    #[test]
+    fn set_block_strings() {
+        assert_eq!(
+            set("a: 1\nb: 2\n", "a", string("x\ny\n")),
+            "a: |\n  x\n  y\nb: 2\n"
+        );
+        assert_eq!(
+            set("a: |\n  x\n  y\nb: 2\n", "a", string("z")),
+            "a: \"z\"\nb: 2\n"
+        );
+        // Anything after the value would become part of a block string.
+        assert_eq!(
+            set("a: 1 # one\nb: 2\n", "a", string("x\ny\n")),
+            "a: \"x\\ny\\n\" # one\nb: 2\n"
+        );
+        // So would comments under the entry, which new entries go after.
+        assert_eq!(
+            set("a: 1\n  # one\nb: 2\n", "a", string("x\ny\n")),
+            "a: \"x\\ny\\n\"\n  # one\nb: 2\n"
+        );
+        assert_eq!(
+            set("b:\n  # TODO\n", "a", string("x\ny\n")),
+            "b:\n  # TODO\na: |\n  x\n  y\n"
+        );
+        assert_eq!(
+            set(
+                "b:\n  # TODO\n\n    # more\n\n# c\nc: 1\n",
+                "b.d",
+                string("x\ny\n")
+            ),
+            "b:\n  # TODO\n\n    # more\n  d: |\n    x\n    y\n\n# c\nc: 1\n"
+        );
+        assert_eq!(
+            set("- 1\n  # one\n", "1", string("x\ny\n")),
+            "- 1\n  # one\n- |\n  x\n  y\n"
+        );
+
+        let value = object(&[("b", string("x\ny\n"))]);
+        assert_eq!(
+            set("a: 1\n      # deep\n", "a", value.clone()),
+            "a:\n      # deep\n  b: |\n    x\n    y\n"
+        );
+
+        // Edits that wouldn't read back as the value are refused.
+        let mut document = Document::parse("a:\n  b: 1\n      # deep\n").unwrap();
+        assert_eq!(
+            document.set(&path("a"), &value),
+            Err(Error::Edit(
+                "the edit would read back as a different value at `a`".to_string()
+            ))
+        );
+        assert_eq!(document.as_str(), "a:\n  b: 1\n      # deep\n");
+    }
+
+    #[test]
+    fn set_missing_values() {
+        assert_eq!(
+            set(DEPLOYMENT, "image.pull", Value::Bool(true)),
+            DEPLOYMENT.replace("# bump me\n", "# bump me\n  pull: true\n")
+        );
+        assert_eq!(
+            set(DEPLOYMENT, "env.2", object(&[("name", string("TZ"))])),
+            DEPLOYMENT.replace("'prod'\n", "'prod'\n    - name: \"TZ\"\n")
+        );
+        assert_eq!(
+            set(DEPLOYMENT, "ports.2", Value::Integer(8080)),
+            DEPLOYMENT.replace("[80,443]", "[80,443, 8080]")
+        );
+        assert_eq!(
+            set("a: 1", "b.c.d", Value::Integer(2)),
+            "a: 1\nb:\n  c:\n    d: 2\n"
+        );
+        assert_eq!(set("", "a", Value::Integer(2)), "a: 2\n");
+        assert_eq!(
+            set("# a: 1\n# b: 1", "a", Value::Integer(2)),
+            "# a: 1\n# b: 1\na: 2\n"
+        );
+        assert_eq!(set("a: {}\n", "a.b", Value::Integer(2)), "a: { b: 2 }\n");
+        assert_eq!(set("a: []\n", "a.0", Value::Integer(2)), "a: [2]\n");
+        assert_eq!(
+            set("a:\nb: 1\n", "a.b", Value::Integer(2)),
+            "a:\n  b: 2\nb: 1\n"
+        );
+        assert_eq!(
+            set("- a: 1\n", "0.b", Value::Integer(2)),
+            "- a: 1\n  b: 2\n"
+        );
+
+        let mut document = Document::parse("a: 1\n").unwrap();
+        assert!(document.set(&path("a.b"), &Value::Null).is_err());
+        assert!(document.set(&path("c.1"), &Value::Null).is_err());
+        assert_eq!(document.as_str(), "a: 1\n");
+    }
+
+    #[test]
+    fn remove_entries() {
+        assert_eq!(
+            remove(DEPLOYMENT, "image.name"),
+            DEPLOYMENT.replace("  name:   \"app\"    # pinned\n", "")
+        );
+        assert_eq!(
+            remove(
+                "a: 1\n# About b.\n  # Not about b.\n# About b.\nb:\n  c: 2\n\n# End.\n",
+                "b"
+            ),
+            "a: 1\n# About b.\n  # Not about b.\n\n# End.\n"
+        );
+        assert_eq!(
+            remove(DEPLOYMENT, "env.0"),
+            DEPLOYMENT.replace("    - name: \"LOG\"\n      value: \"debug\"\n", "")
+        );
+        assert_eq!(
+            remove(DEPLOYMENT, "env.1.name"),
+            DEPLOYMENT.replace("- name: \"MODE\"\n      value", "- value")
+        );
+        assert_eq!(remove("a: [1, 2, 3]", "a.0"), "a: [2, 3]");
+        assert_eq!(remove("a: [1, 2, 3]", "a.1"), "a: [1, 3]");
+        assert_eq!(remove("a: [1, 2, 3,]", "a.2"), "a: [1, 2,]");
+        assert_eq!(remove("a: { b: 1, c: 2 }", "a.c"), "a: { b: 1 }");
+
+        // Collections left empty are written as empty collections, rather than as null.
+        assert_eq!(remove("a:\n  b: 1\nc: 2\n", "a.b"), "a: {}\nc: 2\n");
+        assert_eq!(remove("a:\n  - 1\nc: 2\n", "a.0"), "a: []\nc: 2\n");
+        assert_eq!(remove("a: [1]\n", "a.0"), "a: []\n");
+
+        // The document can be left empty, keeping the comments that aren't about the entry.
+        assert_eq!(remove("a: 1\n", "a"), "");
+        assert_eq!(remove("a.b: 1\n", "a"), "");
+        assert_eq!(
+            remove("# Config.\n\n# About a.\na: 1\n# End.\n", "a"),
+            "# Config.\n\n# End.\n"
+        );
+
+        let mut document = Document::parse("- 1\n").unwrap();
+        assert_eq!(
+            document.remove(&path("0")),
+            Err(Error::Edit(
+                "can't remove `[0]`, as the document can't be an empty array".to_string()
+            ))
+        );
+        assert_eq!(
+            document.remove(&path("b.0")),
+            Err(Error::Edit("there's no value at `b[0]`".to_string()))
+        );
+    }
+
+    #[test]
+    fn insert_after() {
+        let mut document = Document::parse(DEPLOYMENT).unwrap();
+        document
+            .insert_after(
+                &path("image"),
+                PathSegment::Key("tier".into()),
+                &string("web"),
+            )
+            .unwrap();
+        document
+            .insert_after(&path("env.0"), PathSegment::Index(1), &Value::Null)
+            .unwrap();
+        document
+            .insert_after(&path("ports.0"), PathSegment::Index(1), &Value::Integer(81))
+            .unwrap();
+        assert_eq!(
+            document.as_str(),
+            DEPLOYMENT
+                .replace("\nreplicas", "tier: \"web\"\n\nreplicas")
+                .replace("\"debug\"\n", "\"debug\"\n    - null\n")
+                .replace("[80,", "[80, 81,")
+        );
+
+        assert!(document
+            .insert_after(
+                &path("image"),
+                PathSegment::Key("tier".into()),
+                &Value::Null
+            )
+            .is_err());
+        assert!(document
+            .insert_after(&path("env.0"), PathSegment::Index(3), &Value::Null)
+            .is_err());
+        assert!(document
+            .insert_after(&path("env"), PathSegment::Index(1), &Value::Null)
+            .is_err());
+    }
+
+    #[test]
+    fn dotted_keys() {
+        let input = "a.b: 1\nc: 2\na.d: 3\n";
+
+        assert_eq!(
+            set(input, "a.b", Value::Integer(4)),
+            "a.b: 4\nc: 2\na.d: 3\n"
+        );
+        assert_eq!(
+            set(input, "a.e", Value::Integer(4)),
+            "a.b: 1\nc: 2\na.d: 3\na.e: 4\n"
+        );
+        assert_eq!(set(input, "a", Value::Integer(4)), "a: 4\nc: 2\n");
+        assert_eq!(remove(input, "a"), "c: 2\n");
+        assert_eq!(remove(input, "a.d"), "a.b: 1\nc: 2\n");
+        assert_eq!(
+            set("x: { a.b: 1 }", "x.a", Value::Integer(2)),
+            "x: { a: 2 }"
+        );
+    }
+
+    /// Every path in `value`, outermost first.
+    fn paths(value: &Value<'_>, path: &mut Path<'static>, paths: &mut Vec<Path<'static>>) {
+        let nested: Vec<(PathSegment<'static>, &Value<'_>)> = match value {
+            Value::Object(object) => object
+                .iter()
+                .map(|(key, value)| (PathSegment::Key(Cow::Owned(key.to_string())), value))
+                .collect(),
+            Value::Array(array) => array
+                .iter()
+                .enumerate()
+                .map(|(index, value)| (PathSegment::Index(index), value))
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        for (segment, value) in nested {
+            path.push(segment);
+            paths.push(path.clone());
+            self::paths(value, path, paths);
+            path.pop();
+        }
+    }
+
+    fn get_mut<'v, 'a>(value: &'v mut Value<'a>, path: &[PathSegment<'_>]) -> &'v mut Value<'a> {
+        path.iter()
+            .fold(value, |value, segment| match (value, segment) {
+                (Value::Object(object), PathSegment::Key(key)) => &mut object[key.as_ref()],
+                (Value::Array(array), PathSegment::Index(index)) => &mut array[*index],
+                _ => unreachable!(),
+            })
+    }
+
+    const FIXTURES: &[&str] = &[
+        DEPLOYMENT,
+        indoc! {r#"
+            servers:
+              - name: "alpha"
+                port: 8080
+
+              - - 1
+                - [2, { b: 3 }]
+              -
+                  key: |
+                    text
+            matrix: [[1, 2], [ 3 ], []] # comment
+            empty: {}
+            last:
+        "#},
+        indoc! {r#"
+            database.pool.max: 10
+            database:
+              host: "x"
+              pool:
+                min: 1
+            database.pool.idle: 5
+            server . port: 80
+            flow: { a.b: 1, a.c: 2 }
+        "#},
+        "a:\n    b:\n        - c: 1\n          d:\n            - 2",
+        "a:",
+        "- ",
+        "a: 1\n  # under a\nb:\n  c: 2\n      # under c\nd:\n  # under d\n",
+    ];
+
+    #[test]
+    fn edits_every_path() {
+        let replacements = [
+            Value::Integer(7),
+            string("multi\nline\n"),
+            object(&[(
+                "x",
+                Value::Array(vec![Value::Null, object(&[("y", Value::Bool(true))])]),
+            )]),
+            Value::Array(vec![
+                Value::Array(vec![Value::Integer(1)]),
+                Value::Integer(2),
+            ]),
+        ];
+
+        for fixture in FIXTURES {
+            let original = Document::parse(fixture).unwrap();
+            let mut all = Vec::new();
+            paths(original.value(), &mut Vec::new(), &mut all);
+
+            for path in &all {
+                for replacement in replacements.iter() {
+                    let mut document = original.clone();
+                    document.set(path, replacement).unwrap();
+
+                    let mut expected = original.value().clone();
+                    *get_mut(&mut expected, path) = replacement.clone();
+                    assert_eq!(document.value(), &expected, "{:?} in {}", path, document);
+                    assert_eq!(parse(document.as_str()).as_ref(), Ok(&expected));
+                }
+
+                let mut document = original.clone();
+                let result = document.remove(path);
+
+                let (last, parent) = path.split_last().unwrap();
+                let mut expected = original.value().clone();
+                match (get_mut(&mut expected, parent), last) {
+                    (Value::Object(object), PathSegment::Key(key)) => {
+                        object.shift_remove(key.as_ref());
+                    }
+                    (Value::Array(array), PathSegment::Index(index)) => {
+                        array.remove(*index);
+                    }
+                    _ => unreachable!(),
+                }
+
+                if expected == Value::Array(Vec::new()) {
+                    assert!(result.is_err());
+                } else {
+                    assert_eq!(result, Ok(()), "{:?} in {}", path, fixture);
+                    assert_eq!(document.value(), &expected, "{:?} in {}", path, document);
+                }
+            }
+        }
+    }
+}
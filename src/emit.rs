@@ -0,0 +1,825 @@
+use std::cmp::Reverse;
+use std::fmt::{self, Write};
+use std::time::Duration;
+
+use crate::{Error, Map, Value, BYTE_UNITS, DURATION_UNITS};
+
+/// How long a line can get before a flow array is written as a block instead.
+const MAX_WIDTH: usize = 80;
+
+/// The order the keys of objects are written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyOrder {
+    /// The order of the object, which for parsed objects is the order of the input. The default.
+    #[default]
+    Source,
+    Sorted,
+}
+
+/// How strings, and keys that can't be written bare, are quoted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuoteStyle {
+    /// Double quotes, with escapes where they're needed. The default.
+    #[default]
+    Double,
+    /// Single quotes for strings without a `'`, line break or control character, which single
+    /// quoted strings can't have, and double quotes for the rest.
+    Single,
+}
+
+/// How floats are written. Either way they read back as the same float.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FloatFormat {
+    /// As few digits as read back as the same float, like `0.1` or `2.0`. The default.
+    #[default]
+    Shortest,
+    /// As few digits as read back as the same float, padded with zeros to at least this many
+    /// digits after the `.`, like `2.00`.
+    MinFractionDigits(usize),
+}
+
+/// Options to change how values are written. [`emit`] uses the defaults.
+#[derive(Debug, Clone)]
+pub struct EmitOptions {
+    indent: usize,
+    key_order: KeyOrder,
+    quote_style: QuoteStyle,
+    block_strings: bool,
+    float_format: FloatFormat,
+}
+
+impl Default for EmitOptions {
+    fn default() -> Self {
+        Self {
+            indent: 2,
+            key_order: KeyOrder::default(),
+            quote_style: QuoteStyle::default(),
+            block_strings: true,
+            float_format: FloatFormat::default(),
+        }
+    }
+}
+
+impl EmitOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How many spaces each nested object or array is indented past the key it's under. Defaults
+    /// to 2, and can't be less than 1.
+    pub fn indent(mut self, indent: usize) -> Self {
+        self.indent = indent.max(1);
+        self
+    }
+
+    pub fn key_order(mut self, key_order: KeyOrder) -> Self {
+        self.key_order = key_order;
+        self
+    }
+
+    pub fn quote_style(mut self, quote_style: QuoteStyle) -> Self {
+        self.quote_style = quote_style;
+        self
+    }
+
+    /// Whether strings with line breaks are written as block strings (`|`) where they read back
+    /// the same. On by default.
+    pub fn block_strings(mut self, block_strings: bool) -> Self {
+        self.block_strings = block_strings;
+        self
+    }
+
+    pub fn float_format(mut self, float_format: FloatFormat) -> Self {
+        self.float_format = float_format;
+        self
+    }
+
+    /// Writes `value` as a document in the block style, which [`parse`](crate::parse) reads back
+    /// as an equal value. Arrays of scalars that fit on a line are written as flow arrays.
+    ///
+    /// Only objects and non-empty arrays can be documents, with an empty object written as no
+    /// text at all, and floats have to be finite.
+    pub fn emit(&self, value: &Value<'_>) -> Result<String, Error> {
+        let mut emitter = self.emitter(false);
+
+        match value {
+            Value::Object(object) => emitter.object(object, 0)?,
+            Value::Array(array) if !array.is_empty() => emitter.array(array, 0)?,
+            _ => {
+                return Err(Error::Serialize(
+                    "only an object or a non-empty array can be written as a document".to_string(),
+                ))
+            }
+        }
+
+        Ok(emitter.out)
+    }
+
+    fn emitter(&self, lenient: bool) -> Emitter<'_> {
+        Emitter {
+            options: self,
+            lenient,
+            out: String::new(),
+        }
+    }
+}
+
+/// Writes `value` as text with the default options, like [`EmitOptions::emit`].
+pub fn emit(value: &Value<'_>) -> Result<String, Error> {
+    EmitOptions::default().emit(value)
+}
+
+/// The text of `value` after a key or `-`, like [`Emitter::inline`], with the default options.
+pub(crate) fn inline(
+    value: &Value<'_>,
+    used: usize,
+    block_indent: Option<usize>,
+) -> Result<Option<String>, Error> {
+    EmitOptions::default()
+        .emitter(false)
+        .inline(value, used, block_indent)
+}
+
+/// The lines of a non-empty collection, indented by `indent`, with the default options.
+pub(crate) fn block(value: &Value<'_>, indent: usize) -> Result<String, Error> {
+    let options = EmitOptions::default();
+    let mut emitter = options.emitter(false);
+    emitter.block(value, indent)?;
+    Ok(emitter.out)
+}
+
+/// The text of `value` in a flow collection, where collections are written as flow collections
+/// and strings are quoted, with the default options.
+pub(crate) fn flow(value: &Value<'_>) -> Result<String, Error> {
+    EmitOptions::default().emitter(false).flow(value)
+}
+
+/// A key, quoted if it can't be written bare.
+pub(crate) fn key(key: &str) -> String {
+    EmitOptions::default().emitter(false).key(key)
+}
+
+impl fmt::Display for Value<'_> {
+    /// Writes the value like [`emit`]. Values that can't be documents are written the way they
+    /// would be after a key, and floats that aren't finite as `NaN`, `inf` or `-inf`, though those
+    /// can't be parsed.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let options = EmitOptions::default();
+        let mut emitter = options.emitter(true);
+
+        let result = match self {
+            Value::Object(object) => emitter.object(object, 0),
+            Value::Array(array) if !array.is_empty() => emitter.array(array, 0),
+            value => emitter
+                .inline(value, 0, None)
+                .map(|value| emitter.out = value.unwrap_or_default()),
+        };
+
+        match result {
+            Ok(()) => f.write_str(&emitter.out),
+            // Can't happen, as the only errors are from floats that aren't finite.
+            Err(_) => Err(fmt::Error),
+        }
+    }
+}
+
+struct Emitter<'a> {
+    options: &'a EmitOptions,
+    /// Whether floats that aren't finite are written anyway, for `Display`.
+    lenient: bool,
+    out: String,
+}
+
+impl Emitter<'_> {
+    /// Writes each entry of an object on its own line, indented by `indent`.
+    fn object(&mut self, object: &Map<'_>, indent: usize) -> Result<(), Error> {
+        let mut entries = object.iter().collect::<Vec<_>>();
+        if self.options.key_order == KeyOrder::Sorted {
+            entries.sort_by_key(|(key, _)| *key);
+        }
+
+        for (key, value) in entries {
+            let key = self.key(key);
+            let used = indent + key.chars().count() + 2;
+            let nested_indent = indent + self.options.indent;
+
+            self.out.push_str(&" ".repeat(indent));
+            self.out.push_str(&key);
+            self.out.push(':');
+
+            match self.inline(value, used, Some(nested_indent))? {
+                Some(value) => {
+                    self.out.push(' ');
+                    self.out.push_str(&value);
+                    self.out.push('\n');
+                }
+                None => {
+                    self.out.push('\n');
+                    self.block(value, nested_indent)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes each item of a non-empty array on its own line after a `-`, indented by `indent`.
+    /// Collections that don't fit on the line start on the same line as the `-`, with the rest of
+    /// their lines lined up with the first.
+    fn array(&mut self, array: &[Value<'_>], indent: usize) -> Result<(), Error> {
+        let marker = format!("{}- ", " ".repeat(indent));
+
+        for item in array {
+            match self.inline(item, marker.len(), Some(indent + self.options.indent))? {
+                Some(item) => {
+                    self.out.push_str(&marker);
+                    self.out.push_str(&item);
+                    self.out.push('\n');
+                }
+                None => {
+                    let start = self.out.len();
+                    self.block(item, marker.len())?;
+                    self.out.replace_range(start..start + marker.len(), &marker);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes a non-empty collection, with each entry on its own line indented by `indent`.
+    fn block(&mut self, value: &Value<'_>, indent: usize) -> Result<(), Error> {
+        match value {
+            Value::Object(object) => self.object(object, indent),
+            Value::Array(array) => self.array(array, indent),
+            _ => Ok(()),
+        }
+    }
+
+    /// The text of a value that fits on the line after a key or `-`, or `None` for a collection
+    /// that has to be written as a block. `used` is how much of the line comes before the value.
+    /// The lines of a block string are indented by `block_indent`, and block strings can't be
+    /// used when it's `None`.
+    fn inline(
+        &self,
+        value: &Value<'_>,
+        used: usize,
+        block_indent: Option<usize>,
+    ) -> Result<Option<String>, Error> {
+        let text = match value {
+            Value::Object(object) if object.is_empty() => "{}".to_string(),
+            Value::Array(array) if array.is_empty() => "[]".to_string(),
+            Value::Object(_) => return Ok(None),
+            Value::Array(array) => {
+                // Only arrays of scalars are written as flow arrays.
+                let mut items = Vec::with_capacity(array.len());
+                for item in array {
+                    match item {
+                        Value::Object(o) if !o.is_empty() => return Ok(None),
+                        Value::Array(a) if !a.is_empty() => return Ok(None),
+                        _ => items.push(self.inline(item, 0, None)?.unwrap_or_default()),
+                    }
+                }
+
+                let flow = format!("[{}]", items.join(", "));
+                if used + flow.chars().count() > MAX_WIDTH {
+                    return Ok(None);
+                }
+                flow
+            }
+            Value::Null => "null".to_string(),
+            Value::String(string) => {
+                let block = block_indent
+                    .filter(|_| self.options.block_strings)
+                    .and_then(|indent| block_string(string, indent));
+                match block {
+                    Some(block) => block,
+                    None => self.quote(string),
+                }
+            }
+            Value::Integer(integer) => integer.to_string(),
+            Value::Float(float) => self.float(*float)?,
+            Value::Bool(bool) => bool.to_string(),
+            Value::DateTime(date_time) => date_time.to_string(),
+            Value::Date(date) => date.to_string(),
+            Value::Time(time) => time.to_string(),
+            Value::Duration(duration) => write_duration(*duration),
+            Value::Bytes(bytes) => write_bytes(*bytes),
+        };
+
+        Ok(Some(text))
+    }
+
+    fn flow(&self, value: &Value<'_>) -> Result<String, Error> {
+        let text = match value {
+            Value::Object(object) if !object.is_empty() => {
+                let mut entries = Vec::with_capacity(object.len());
+                for (key, value) in object {
+                    entries.push(format!("{}: {}", self.key(key), self.flow(value)?));
+                }
+                format!("{{ {} }}", entries.join(", "))
+            }
+            Value::Array(array) => {
+                let mut items = Vec::with_capacity(array.len());
+                for item in array {
+                    items.push(self.flow(item)?);
+                }
+                format!("[{}]", items.join(", "))
+            }
+            value => self.inline(value, 0, None)?.unwrap_or_default(),
+        };
+
+        Ok(text)
+    }
+
+    fn key(&self, key: &str) -> String {
+        if is_bare_key(key) {
+            key.to_string()
+        } else {
+            self.quote(key)
+        }
+    }
+
+    fn quote(&self, string: &str) -> String {
+        let raw = self.options.quote_style == QuoteStyle::Single
+            && !string.contains('\'')
+            && !string.chars().any(|c| c.is_control() && c != '\t');
+
+        if raw {
+            format!("'{}'", string)
+        } else {
+            quote(string)
+        }
+    }
+
+    /// A float with a `.` so it reads back as a float. `f64`'s `Display` never uses an exponent,
+    /// which numbers can't have.
+    fn float(&self, float: f64) -> Result<String, Error> {
+        if !float.is_finite() {
+            if self.lenient {
+                return Ok(float.to_string());
+            }
+            return Err(Error::Serialize(format!(
+                "{} can't be written, as numbers have to be finite",
+                float
+            )));
+        }
+
+        let mut text = float.to_string();
+        if !text.contains('.') {
+            text.push_str(".0");
+        }
+
+        if let FloatFormat::MinFractionDigits(digits) = self.options.float_format {
+            let fraction = text.len() - text.find('.').map_or(text.len(), |i| i + 1);
+            for _ in fraction..digits {
+                text.push('0');
+            }
+        }
+
+        Ok(text)
+    }
+}
+
+/// Whether `key` can be written without quotes, as words of letters, digits, `_` and `-`
+/// separated by spaces.
+pub(crate) fn is_bare_key(key: &str) -> bool {
+    !key.is_empty()
+        && !key.starts_with(' ')
+        && !key.ends_with(' ')
+        // A key starting with `- ` would be an array item.
+        && !key.starts_with("- ")
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == ' ')
+}
+
+/// A double quoted string, with escapes for `"`, `\` and control characters.
+pub(crate) fn quote(string: &str) -> String {
+    let mut quoted = String::with_capacity(string.len() + 2);
+    quoted.push('"');
+
+    for c in string.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{{{:x}}}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+/// A literal block string for a string with line breaks, with its lines indented by `indent`,
+/// if it would read back the same. It wouldn't if its first line is indented, as that
+/// indentation is taken to be the block's, or if it has lines of only spaces, which read back as
+/// empty lines.
+fn block_string(string: &str, indent: usize) -> Option<String> {
+    let content = string.trim_end_matches('\n');
+    let trailing_newlines = string.len() - content.len();
+
+    let first_line = content.split('\n').find(|line| !line.is_empty())?;
+    if !string.contains('\n')
+        || first_line.starts_with(' ')
+        || string
+            .chars()
+            .any(|c| c.is_control() && c != '\n' && c != '\t')
+        || content
+            .split('\n')
+            .any(|line| !line.is_empty() && line.trim_start_matches(' ').is_empty())
+    {
+        return None;
+    }
+
+    let mut block = match trailing_newlines {
+        0 => "|-",
+        1 => "|",
+        _ => "|+",
+    }
+    .to_string();
+
+    for line in content.split('\n') {
+        block.push('\n');
+        if !line.is_empty() {
+            block.push_str(&" ".repeat(indent));
+            block.push_str(line);
+        }
+    }
+    // The line break ending the last line is written after the value.
+    for _ in 1..trailing_newlines {
+        block.push('\n');
+    }
+
+    Some(block)
+}
+
+/// A duration using each unit that's needed, largest first, like `1h30m`.
+fn write_duration(duration: Duration) -> String {
+    let mut units = DURATION_UNITS
+        .iter()
+        .filter(|(unit, _)| *unit != "µs")
+        .collect::<Vec<_>>();
+    units.sort_by_key(|(_, nanoseconds)| Reverse(*nanoseconds));
+
+    let mut rest = duration.as_nanos();
+    let mut text = String::new();
+    for (unit, nanoseconds) in units {
+        if rest >= *nanoseconds {
+            let _ = write!(text, "{}{}", rest / nanoseconds, unit);
+            rest %= nanoseconds;
+        }
+    }
+
+    if text.is_empty() {
+        text.push_str("0s");
+    }
+    text
+}
+
+/// A byte size using the largest unit that divides it exactly, like `512KiB`.
+fn write_bytes(bytes: u64) -> String {
+    if bytes == 0 {
+        return "0B".to_string();
+    }
+
+    let bytes = u128::from(bytes);
+    let (unit, size) = BYTE_UNITS
+        .iter()
+        .filter(|(_, size)| bytes.is_multiple_of(*size))
+        .max_by_key(|(_, size)| *size)
+        .unwrap_or(&("B", 1));
+
+    format!("{}{}", bytes / size, unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use indoc::indoc;
+
+    /// Writes `value` as the only entry in a document and parses it back.
+    fn round_trip(value: Value<'static>) -> (String, Value<'static>) {
+        let mut object = Map::new();
+        object.insert("n".into(), value);
+        let text = emit(&Value::Object(object)).unwrap();
+
+        let value = match parse(Box::leak(text.clone().into_boxed_str())) {
+            Ok(Value::Object(mut object)) => object.swap_remove("n").unwrap(),
+            result => panic!("{:?} from {:?}", result, text),
+        };
+        (text, value)
+    }
+
+    #[test]
+    fn scalars() {
+        let scalars = [
+            (Value::Null, "null"),
+            (Value::Bool(true), "true"),
+            (Value::Integer(-42), "-42"),
+            (Value::Integer(i64::MIN), "-9223372036854775808"),
+            (Value::Float(1.0), "1.0"),
+            (Value::Float(-0.5), "-0.5"),
+            (Value::Float(1e20), "100000000000000000000.0"),
+            (Value::Float(1e-7), "0.0000001"),
+            (Value::Float(f64::MAX), &format!("{}.0", f64::MAX)),
+            (Value::String("".into()), r#""""#),
+            (
+                Value::String("a\"b\\c\nd\te\r".into()),
+                r#""a\"b\\c\nd\te\u{d}""#,
+            ),
+            (
+                Value::String("# not a comment".into()),
+                r##""# not a comment""##,
+            ),
+            (Value::Duration(Duration::from_secs(0)), "0s"),
+            (Value::Duration(Duration::new(5400, 0)), "1h30m"),
+            (
+                Value::Duration(Duration::new(93_784, 5_006_007)),
+                "1d2h3m4s5ms6us7ns",
+            ),
+            (Value::Bytes(0), "0B"),
+            (Value::Bytes(1000), "1KB"),
+            (Value::Bytes(512 * 1024), "512KiB"),
+            (Value::Bytes(1500), "1500B"),
+            (Value::Bytes(u64::MAX), "18446744073709551615B"),
+        ];
+
+        for (value, text) in scalars.iter().cloned() {
+            let expected = value.clone();
+            assert_eq!(round_trip(value), (format!("n: {}\n", text), expected));
+        }
+    }
+
+    #[test]
+    fn dates_and_times() {
+        for text in [
+            "2024-02-29",
+            "09:30:00",
+            "23:59:60.5",
+            "2024-02-29T09:30:00Z",
+            "2024-02-29T09:30:00.000000001-05:30",
+            "2024-02-29T09:30:00",
+        ] {
+            let input = format!("n: {}\n", text);
+            let value = parse(&input).unwrap();
+            assert_eq!(emit(&value).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn non_finite_floats() {
+        let mut object = Map::new();
+        object.insert("n".into(), Value::Float(f64::INFINITY));
+
+        assert!(emit(&Value::Object(object)).is_err());
+    }
+
+    #[test]
+    fn options() {
+        let input = indoc! {r#"
+            name: "ooml"
+            ratio: 0.5
+            nested:
+              zebra: 1.0
+              apple: "it's"
+              list:
+                - b: 2
+                  a: [1, 2]
+        "#};
+        let options = EmitOptions::new()
+            .indent(4)
+            .key_order(KeyOrder::Sorted)
+            .quote_style(QuoteStyle::Single)
+            .float_format(FloatFormat::MinFractionDigits(2));
+
+        let value = parse(input).unwrap();
+        let text = options.emit(&value).unwrap();
+        assert_eq!(
+            text,
+            indoc! {r#"
+                name: 'ooml'
+                nested:
+                    apple: "it's"
+                    list:
+                        - a: [1, 2]
+                          b: 2
+                    zebra: 1.00
+                ratio: 0.50
+            "#}
+        );
+        assert_eq!(parse(&text).unwrap(), value);
+
+        assert_eq!(
+            EmitOptions::new().indent(0).emit(&value),
+            EmitOptions::new().indent(1).emit(&value)
+        );
+    }
+
+    #[test]
+    fn block_strings() {
+        let strings = [
+            ("line 1\n  line 2", "|-\n  line 1\n    line 2"),
+            ("line 1\n\nline 2\n", "|\n  line 1\n\n  line 2"),
+            ("line 1\n\n\n", "|+\n  line 1\n\n"),
+            ("\n\nline 1\n", "|\n\n\n  line 1"),
+            ("\tline 1\nline 2\n", "|\n  \tline 1\n  line 2"),
+            // These would read back differently as block strings.
+            ("  line 1\nline 2\n", r#""  line 1\nline 2\n""#),
+            ("line 1\n  \nline 2\n", r#""line 1\n  \nline 2\n""#),
+            ("line 1\r\nline 2\n", r#""line 1\u{d}\nline 2\n""#),
+            ("\n\n", r#""\n\n""#),
+        ];
+
+        for (string, text) in strings.iter() {
+            let value = Value::String((*string).into());
+            assert_eq!(round_trip(value.clone()), (format!("n: {}\n", text), value));
+        }
+
+        let mut object = Map::new();
+        object.insert("n".into(), Value::String("a\nb\n".into()));
+        let value = Value::Array(vec![Value::Object(object)]);
+        assert_eq!(emit(&value).unwrap(), "- n: |\n    a\n    b\n");
+        assert_eq!(
+            EmitOptions::new()
+                .block_strings(false)
+                .emit(&value)
+                .unwrap(),
+            "- n: \"a\\nb\\n\"\n"
+        );
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Value::Null.to_string(), "null");
+        assert_eq!(Value::Float(2.0).to_string(), "2.0");
+        assert_eq!(Value::Float(f64::NAN).to_string(), "NaN");
+        assert_eq!(Value::String("a\nb".into()).to_string(), r#""a\nb""#);
+        assert_eq!(Value::Array(vec![]).to_string(), "[]");
+        assert_eq!(Value::Object(Map::new()).to_string(), "");
+
+        let value = parse("a: [1, 2]\nb:\n  c: \"d\"\n").unwrap();
+        assert_eq!(value.to_string(), "a: [1, 2]\nb:\n  c: \"d\"\n");
+    }
+
+    fn all_options() -> Vec<EmitOptions> {
+        let mut all = Vec::new();
+        for indent in [1, 2, 4] {
+            for key_order in [KeyOrder::Source, KeyOrder::Sorted] {
+                for quote_style in [QuoteStyle::Double, QuoteStyle::Single] {
+                    for block_strings in [true, false] {
+                        for float_format in
+                            [FloatFormat::Shortest, FloatFormat::MinFractionDigits(3)]
+                        {
+                            all.push(
+                                EmitOptions::new()
+                                    .indent(indent)
+                                    .key_order(key_order)
+                                    .quote_style(quote_style)
+                                    .block_strings(block_strings)
+                                    .float_format(float_format),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        all
+    }
+
+    #[test]
+    fn fixtures() {
+        for fixture in crate::fixtures::DOCUMENTS {
+            let value = parse(fixture).unwrap_or_else(|e| panic!("{} in {:?}", e, fixture));
+
+            for options in all_options() {
+                let text = options.emit(&value).unwrap();
+                assert_eq!(
+                    parse(&text),
+                    Ok(value.clone()),
+                    "{:?} from {:?}",
+                    text,
+                    options
+                );
+            }
+        }
+    }
+
+    mod round_trips {
+        use super::*;
+        use crate::{Date, DateTime, Offset, Time};
+        use proptest::prelude::*;
+
+        fn date() -> impl Strategy<Value = Date> {
+            (0..=9999u16, 1..=12u8, 1..=31u8)
+                .prop_filter_map("invalid date", |(y, m, d)| Date::new(y, m, d))
+        }
+
+        fn time() -> impl Strategy<Value = Time> {
+            (0..24u8, 0..60u8, 0..=60u8, 0..1_000_000_000u32)
+                .prop_filter_map("invalid time", |(h, m, s, n)| Time::new(h, m, s, n))
+        }
+
+        fn date_time() -> impl Strategy<Value = DateTime> {
+            let offset = prop_oneof![Just(None), (-24 * 60 + 1..24 * 60i16).prop_map(Offset::new),];
+            (date(), time(), offset).prop_map(|(date, time, offset)| DateTime {
+                date,
+                time,
+                offset,
+            })
+        }
+
+        fn scalar() -> impl Strategy<Value = Value<'static>> {
+            prop_oneof![
+                Just(Value::Null),
+                any::<bool>().prop_map(Value::Bool),
+                any::<i64>().prop_map(Value::Integer),
+                any::<f64>()
+                    .prop_filter("not finite", |f| f.is_finite())
+                    .prop_map(Value::Float),
+                any::<String>().prop_map(|s| Value::String(s.into())),
+                "[a-z \n\t']{0,20}".prop_map(|s| Value::String(s.into())),
+                date().prop_map(Value::Date),
+                time().prop_map(Value::Time),
+                date_time().prop_map(Value::DateTime),
+                (any::<u64>(), 0..1_000_000_000u32)
+                    .prop_map(|(s, n)| Value::Duration(Duration::new(s, n))),
+                any::<u64>().prop_map(Value::Bytes),
+                (0..16u64, 0..7u32).prop_map(|(n, e)| Value::Bytes(n * 1024u64.pow(e))),
+            ]
+        }
+
+        fn key() -> impl Strategy<Value = String> {
+            prop_oneof![any::<String>(), "[a-z_ .-]{0,10}"]
+        }
+
+        fn value() -> impl Strategy<Value = Value<'static>> {
+            scalar().prop_recursive(4, 64, 8, |inner| {
+                prop_oneof![
+                    proptest::collection::vec(inner.clone(), 0..8).prop_map(Value::Array),
+                    proptest::collection::vec((key(), inner), 0..8).prop_map(|entries| {
+                        Value::Object(entries.into_iter().map(|(k, v)| (k.into(), v)).collect())
+                    }),
+                ]
+            })
+        }
+
+        fn document() -> impl Strategy<Value = Value<'static>> {
+            value().prop_filter("not a document", |value| match value {
+                Value::Object(_) => true,
+                Value::Array(array) => !array.is_empty(),
+                _ => false,
+            })
+        }
+
+        fn options() -> impl Strategy<Value = EmitOptions> {
+            (
+                1..6usize,
+                any::<bool>(),
+                any::<bool>(),
+                any::<bool>(),
+                proptest::option::of(0..4usize),
+            )
+                .prop_map(|(indent, sorted, single, block_strings, digits)| {
+                    EmitOptions::new()
+                        .indent(indent)
+                        .key_order(if sorted {
+                            KeyOrder::Sorted
+                        } else {
+                            KeyOrder::Source
+                        })
+                        .quote_style(if single {
+                            QuoteStyle::Single
+                        } else {
+                            QuoteStyle::Double
+                        })
+                        .block_strings(block_strings)
+                        .float_format(
+                            digits.map_or(FloatFormat::Shortest, FloatFormat::MinFractionDigits),
+                        )
+                })
+        }
+
+        proptest! {
+            #[test]
+            fn parse_emit(value in document(), options in options()) {
+                let text = options.emit(&value).unwrap();
+                prop_assert_eq!(parse(&text), Ok(value), "{:?}", text);
+            }
+
+            #[test]
+            fn format_emitted(value in document(), options in options()) {
+                let text = options.emit(&value).unwrap();
+                let formatted = crate::format(&text).unwrap();
+                prop_assert_eq!(parse(&formatted), Ok(value), "{:?}", formatted);
+                prop_assert_eq!(crate::format(&formatted), Ok(formatted.clone()), "{:?}", text);
+            }
+        }
+    }
+}
@@ -0,0 +1,354 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use thiserror::Error;
+
+/// A location in the input. Lines and columns start at 1, and columns count characters rather
+/// than bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    /// The byte offset from the start of the input.
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    /// The position of the byte `offset` in `input`.
+    pub(crate) fn new(input: &str, offset: usize) -> Self {
+        let before = &input[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+        Self {
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum Error {
+    #[error("unexpected token at {0}")]
+    UnexpectedToken(Position),
+    #[error("expected a value at {0}")]
+    ExpectedValue(Position),
+    #[error("expected `:` after key at {0}")]
+    MissingColon(Position),
+    #[error("invalid escape sequence at {0}")]
+    InvalidEscape(Position),
+    #[error("unterminated string at {0}")]
+    UnterminatedString(Position),
+    #[error("unterminated raw string at {0}")]
+    UnterminatedRawString(Position),
+    #[error("invalid number at {0}: {1}")]
+    InvalidNumber(Position, NumberError),
+    #[error("invalid byte size at {0}: {1}")]
+    InvalidByteSize(Position, ByteSizeError),
+    #[error("invalid date or time at {0}")]
+    InvalidDateTime(Position),
+    #[error("invalid duration at {0}")]
+    InvalidDuration(Position),
+    #[error("unclosed `[` or `{{` at {0}")]
+    UnclosedFlow(Position),
+    #[error("bad indentation at {0}")]
+    BadIndent(Position),
+    #[error("collections nested too deeply at {0}")]
+    TooDeep(Position),
+    #[error("duplicate key `{key}` at {second}, first defined at {first}")]
+    DuplicateKey {
+        key: String,
+        first: Position,
+        second: Position,
+    },
+    /// A key was defined as an object, with a dotted key or a nested block, and also as something
+    /// other than an object.
+    #[error("key `{key}` at {second} conflicts with its definition at {first}")]
+    ConflictingKey {
+        key: String,
+        first: Position,
+        second: Position,
+    },
+    /// A value couldn't be deserialized into the type asked for. The position is of the value,
+    /// when it was deserialized from input rather than from a [`Value`](crate::Value).
+    #[error("{message}{}", .position.map_or(String::new(), |p| format!(" at {}", p)))]
+    Deserialize {
+        message: String,
+        position: Option<Position>,
+    },
+    /// A value couldn't be serialized, or can't be written as ooml.
+    #[error("{0}")]
+    Serialize(String),
+    /// A [`Document`](crate::Document) couldn't be edited, because the path doesn't exist or the
+    /// edit can't be made there.
+    #[error("{0}")]
+    Edit(String),
+}
+
+impl Error {
+    pub(crate) fn new(input: &str, error: nom::Err<ParseError<'_>>) -> Self {
+        let error = match error {
+            nom::Err::Error(error) | nom::Err::Failure(error) => error,
+            // All of the parsers are complete, but just in case.
+            nom::Err::Incomplete(_) => {
+                return Error::UnexpectedToken(Position::new(input, input.len()))
+            }
+        };
+        let position = Position::new(input, input.len() - error.input.len());
+
+        match error.kind {
+            ErrorKind::ExpectedValue => Error::ExpectedValue(position),
+            ErrorKind::MissingColon => Error::MissingColon(position),
+            ErrorKind::InvalidEscape => Error::InvalidEscape(position),
+            ErrorKind::UnterminatedString => Error::UnterminatedString(position),
+            ErrorKind::UnterminatedRawString => Error::UnterminatedRawString(position),
+            ErrorKind::InvalidNumber(reason) => Error::InvalidNumber(position, reason),
+            ErrorKind::InvalidByteSize(reason) => Error::InvalidByteSize(position, reason),
+            ErrorKind::InvalidDateTime => Error::InvalidDateTime(position),
+            ErrorKind::InvalidDuration => Error::InvalidDuration(position),
+            ErrorKind::UnclosedFlow => Error::UnclosedFlow(position),
+            ErrorKind::BadIndent => Error::BadIndent(position),
+            ErrorKind::TooDeep => Error::TooDeep(position),
+            ErrorKind::DuplicateKey { key, first } => Error::DuplicateKey {
+                key: key.to_string(),
+                first: Position::new(input, input.len() - first.len()),
+                second: position,
+            },
+            ErrorKind::ConflictingKey { key, first } => Error::ConflictingKey {
+                key: key.to_string(),
+                first: Position::new(input, input.len() - first.len()),
+                second: position,
+            },
+            ErrorKind::Nom(_) => Error::UnexpectedToken(position),
+        }
+    }
+
+    /// Where in the input the error occurred. For duplicate and conflicting keys this is the
+    /// second appearance of the key.
+    pub fn position(&self) -> Option<Position> {
+        let position = match self {
+            Error::UnexpectedToken(position)
+            | Error::ExpectedValue(position)
+            | Error::MissingColon(position)
+            | Error::InvalidEscape(position)
+            | Error::UnterminatedString(position)
+            | Error::UnterminatedRawString(position)
+            | Error::InvalidNumber(position, _)
+            | Error::InvalidByteSize(position, _)
+            | Error::InvalidDateTime(position)
+            | Error::InvalidDuration(position)
+            | Error::UnclosedFlow(position)
+            | Error::BadIndent(position)
+            | Error::TooDeep(position)
+            | Error::DuplicateKey {
+                second: position, ..
+            }
+            | Error::ConflictingKey {
+                second: position, ..
+            } => position,
+            Error::Deserialize { position, .. } => return *position,
+            Error::Serialize(_) | Error::Edit(_) => return None,
+        };
+
+        Some(*position)
+    }
+}
+
+/// Why something that looked like a number couldn't be parsed as one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum NumberError {
+    #[error("numbers can't start with `+`")]
+    LeadingPlus,
+    #[error("decimal numbers can't have leading zeros")]
+    LeadingZero,
+    /// E.g. `.5`.
+    #[error("expected a digit before the `.`")]
+    MissingIntegerDigits,
+    /// E.g. `1.`.
+    #[error("expected a digit after the `.`")]
+    MissingFractionDigits,
+    /// E.g. `3.1.4`.
+    #[error("numbers can only have one `.`")]
+    MultipleDecimalPoints,
+    #[error("exponents aren't supported")]
+    Exponent,
+    /// An `_` that isn't between two digits.
+    #[error("`_` can only be used between digits")]
+    MisplacedUnderscore,
+    /// E.g. `-0xff`.
+    #[error("numbers with a `0x`, `0o` or `0b` prefix can't have a sign")]
+    SignedPrefix,
+    /// A `0x`, `0o` or `0b` prefix with nothing after it.
+    #[error("expected digits after the prefix")]
+    MissingDigits,
+    /// E.g. `0b102`.
+    #[error("invalid digit for the number's base")]
+    InvalidDigit,
+    /// `inf`, `infinity` or `nan`.
+    #[error("infinity and NaN aren't supported")]
+    NonFinite,
+    /// An integer outside the range of an `i64`, or a float too large for an `f64`.
+    #[error("number is too large")]
+    Overflow,
+    /// E.g. `10px`.
+    #[error("unexpected characters after the number")]
+    TrailingCharacters,
+}
+
+/// Why something that looked like a byte size couldn't be parsed as one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum ByteSizeError {
+    /// E.g. `10Mb`.
+    #[error("unknown unit")]
+    UnknownUnit,
+    /// E.g. `1.5B`.
+    #[error("not a whole number of bytes")]
+    Fractional,
+    /// More than `u64::MAX` bytes.
+    #[error("byte size is too large")]
+    Overflow,
+}
+
+/// Errors from the accessors on [`Value`](crate::Value) that convert it to other types.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum ConversionError {
+    #[error("value is not a number")]
+    NotANumber,
+    /// The number is out of range of the target type, or would lose precision converting to it.
+    #[error("number can't be converted to {0} without loss")]
+    Lossy(&'static str),
+    #[error("value is not a byte size")]
+    NotAByteSize,
+    #[error("value is not a duration")]
+    NotADuration,
+    #[error("value is not a date-time")]
+    NotADateTime,
+    /// A local date-time, which isn't a point in time without a time zone.
+    #[error("date-time has no offset")]
+    NoOffset,
+    #[error("date-time is out of the range of {0}")]
+    OutOfRange(&'static str),
+}
+
+/// The error used internally by the parsers, which is turned into an [`Error`] once parsing has
+/// failed.
+#[derive(Debug, PartialEq)]
+pub(crate) struct ParseError<'a> {
+    /// The remaining input at the point the error occurred.
+    pub input: &'a str,
+    pub kind: ErrorKind<'a>,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum ErrorKind<'a> {
+    /// Something other than a value followed a key or array item.
+    ExpectedValue,
+    /// A key wasn't followed by a `:`.
+    MissingColon,
+    /// A backslash in a string wasn't followed by a known escape sequence.
+    InvalidEscape,
+    /// A string was opened but never closed on the same line.
+    UnterminatedString,
+    /// A single quoted raw string was opened but never closed on the same line.
+    UnterminatedRawString,
+    /// Something that started like a number didn't follow the number grammar.
+    InvalidNumber(NumberError),
+    /// Something that looked like a byte size couldn't be parsed as one.
+    InvalidByteSize(ByteSizeError),
+    /// Something that started like a date or time wasn't a valid one, e.g. `2023-02-29`.
+    InvalidDateTime,
+    /// Something that started like a duration wasn't a valid one, e.g. `1m1h`.
+    InvalidDuration,
+    /// A flow array or object wasn't closed on the line it was opened on.
+    UnclosedFlow,
+    /// A line was indented when it shouldn't have been, dedented to a level that doesn't match
+    /// any enclosing block, or indented using tabs.
+    BadIndent,
+    /// Collections were nested more than `MAX_DEPTH` levels deep.
+    TooDeep,
+    /// A key appeared twice in the same object. `first` is the input at its first appearance.
+    DuplicateKey {
+        key: Cow<'a, str>,
+        first: &'a str,
+    },
+    /// A key was defined as both an object and something else. `first` is the input at the
+    /// entry it was first defined by.
+    ConflictingKey {
+        key: Cow<'a, str>,
+        first: &'a str,
+    },
+    Nom(nom::error::ErrorKind),
+}
+
+impl<'a> ParseError<'a> {
+    pub fn new(input: &'a str, kind: ErrorKind<'a>) -> Self {
+        Self { input, kind }
+    }
+}
+
+impl<'a> nom::error::ParseError<&'a str> for ParseError<'a> {
+    fn from_error_kind(input: &'a str, kind: nom::error::ErrorKind) -> Self {
+        Self::new(input, ErrorKind::Nom(kind))
+    }
+
+    fn append(_: &'a str, _: nom::error::ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn position() {
+        let input = "a: 1\nbé: \"x\nc";
+
+        assert_eq!(
+            Position::new(input, 0),
+            Position {
+                offset: 0,
+                line: 1,
+                column: 1
+            }
+        );
+        assert_eq!(
+            Position::new(input, 5),
+            Position {
+                offset: 5,
+                line: 2,
+                column: 1
+            }
+        );
+        // `é` is two bytes but one column.
+        assert_eq!(
+            Position::new(input, 10),
+            Position {
+                offset: 10,
+                line: 2,
+                column: 5
+            }
+        );
+        assert_eq!(
+            Position::new(input, input.len()),
+            Position {
+                offset: 14,
+                line: 3,
+                column: 2
+            }
+        );
+    }
+
+    #[test]
+    fn display() {
+        let error = Error::UnterminatedString(Position::new("a: 1\nb: \"x", 8));
+
+        assert_eq!(error.to_string(), "unterminated string at 2:4");
+    }
+}
@@ -0,0 +1,430 @@
+//! Documents shared by the tests of the parser, emitter and formatter. Each is valid, and all
+//! but `EMPTY` parse to a non-empty document.
+
+use indoc::indoc;
+
+/// Every fixture, for tests that go through them all.
+pub(crate) const DOCUMENTS: &[&str] = &[
+    NESTED,
+    NESTED_DOUBLE,
+    NESTED_DEEP_WITH_DEDENTS,
+    NESTED_WITH_DIFFERENT_INDENT_WIDTHS,
+    BLOCK_STRING_NESTED,
+    NESTED_THEN_UNNESTED,
+    BIG_ARRAY,
+    ARRAY_OF_OBJECTS,
+    ARRAY_ITEM_NESTED_ON_NEXT_LINE,
+    ARRAY_COMPACT_NESTED_ARRAYS,
+    BIG_OBJECT,
+    IT_WORKS,
+    SIMPLE_ARRAY,
+    SIMPLE_OBJECT,
+    BARE_KEYS,
+    UNICODE_KEYS,
+    QUOTED_KEYS,
+    DOTTED_KEYS,
+    DOTTED_KEYS_NESTED,
+    NULL,
+    RAW_STRING,
+    BLOCK_STRING_LITERAL,
+    BLOCK_STRING_FOLDED,
+    BLOCK_STRING_EMPTY,
+    COMMENTS,
+    COMMENT_IN_RAW_AND_BLOCK_STRINGS,
+    COMMENT_AFTER_BLOCK_STRING_HEADER,
+    OBJECT_ORDER,
+    INTEGERS_AND_FLOATS,
+    DATES_AND_TIMES,
+    FLOW_COLLECTIONS,
+    FLOW_COLLECTIONS_BLOCK,
+    KEYS,
+    DOTTED,
+    STRINGS,
+    NUMBERS,
+    TIMES_AND_SIZES,
+    FLOW,
+    EMPTY,
+];
+
+pub(crate) const NESTED: &str = indoc! {r#"
+    key_1: 123
+    obj:
+        nested: 456
+"#};
+
+pub(crate) const NESTED_DOUBLE: &str = indoc! {r#"
+    key_1: 123
+    obj:
+        nested:
+            nested_again: 789
+"#};
+
+pub(crate) const NESTED_DEEP_WITH_DEDENTS: &str = indoc! {r#"
+    a:
+      b:
+        c:
+          d: 1
+      e: 2
+    f:
+      - 3
+      - 4
+"#};
+
+pub(crate) const NESTED_WITH_DIFFERENT_INDENT_WIDTHS: &str = indoc! {r#"
+    a:
+     b:
+          c: 1
+     d: 2
+"#};
+
+pub(crate) const BLOCK_STRING_NESTED: &str = indoc! {r#"
+    obj:
+        text: |
+            line 1
+              line 2
+        next: 1
+"#};
+
+pub(crate) const NESTED_THEN_UNNESTED: &str = indoc! {r#"
+    key_1: 123
+    obj:
+        nested: 456
+    top_level: 789
+"#};
+
+pub(crate) const BIG_ARRAY: &str = indoc! {r#"
+    - 123
+    - "a string!"
+    - 1.5
+    - true
+    - false
+"#};
+
+pub(crate) const ARRAY_OF_OBJECTS: &str = indoc! {r#"
+    servers:
+      - name: "alpha"
+        port: 8080
+      - name: "beta"
+        port: 8081
+        tags:
+          - "a"
+          - "b"
+"#};
+
+pub(crate) const ARRAY_ITEM_NESTED_ON_NEXT_LINE: &str = indoc! {r#"
+    -
+      - 1
+      - 2
+    -
+        key: 3
+"#};
+
+pub(crate) const ARRAY_COMPACT_NESTED_ARRAYS: &str = indoc! {r#"
+    - - 1
+      - - 2
+        - 3
+    - 4
+"#};
+
+pub(crate) const BIG_OBJECT: &str = indoc! {r#"
+    key_1: 123
+    keytwo: "a string!"
+    afloat: 1.5
+    truthy: true
+    falsey: false"#};
+
+pub(crate) const IT_WORKS: &str = indoc! {r#"
+    key_1: 123
+    key_2: "a string!"
+    a_float: 1.5
+    truthy: true
+    falsey: false
+    obj:
+        nested: 456
+"#};
+
+pub(crate) const SIMPLE_ARRAY: &str = indoc! {r#"
+    - 1
+    - 2
+"#};
+
+pub(crate) const SIMPLE_OBJECT: &str = indoc! {r#"
+    keyone: 123
+    keytwo: 456"#};
+
+pub(crate) const BARE_KEYS: &str = indoc! {r#"
+    kebab-case: 1
+    snake_case   : 2
+    spaced  out key: 3
+    -leading-dash: 4
+    123: 5
+    flow: { inner key : 6 }
+"#};
+
+pub(crate) const UNICODE_KEYS: &str = indoc! {r#"
+    ключ: 1
+    名前: 2
+    café au lait: 3
+    Δt: 4
+"#};
+
+pub(crate) const QUOTED_KEYS: &str = indoc! {r#"
+    "x-request-id": 1
+    "a.b/c: d\n": 2
+    'C:\path': 3
+    "": 4
+    "\u{1F600}" : 5
+    "nested":
+      "key": 6
+"#};
+
+pub(crate) const DOTTED_KEYS: &str = indoc! {r#"
+    database.pool.max: 10
+    database:
+      host: "x"
+      pool:
+        min: 1
+    database.pool.idle: 5
+    server . port: 80
+    "a.b".c: 1
+    flow: { a.b: 1, a.c: 2 }
+"#};
+
+pub(crate) const DOTTED_KEYS_NESTED: &str = indoc! {r#"
+    database:
+      pool:
+        max: 10
+        min: 1
+        idle: 5
+      host: "x"
+    server:
+      port: 80
+    "a.b":
+      c: 1
+    flow:
+      a:
+        b: 1
+        c: 2
+"#};
+
+pub(crate) const NULL: &str = indoc! {r#"
+    a: null
+    b: ~
+    c:
+    d: # unset
+    e:
+      f:
+
+    g:
+      - null
+      -
+      - ~
+    h: 1
+"#};
+
+pub(crate) const RAW_STRING: &str = indoc! {r#"
+    path: 'C:\Users\odin'
+    regex: '^\d+\.\d+$'
+    quotes: '"double" quotes'
+    empty: ''"#};
+
+pub(crate) const BLOCK_STRING_LITERAL: &str = indoc! {r#"
+    sql: |
+      SELECT *
+      FROM users
+        WHERE id = 1
+
+    next: 1
+"#};
+
+pub(crate) const BLOCK_STRING_FOLDED: &str = indoc! {r#"
+    text: >
+        a long
+        line
+
+        another paragraph
+"#};
+
+pub(crate) const BLOCK_STRING_EMPTY: &str = indoc! {r#"
+    key_1: |
+    key_2: |+
+"#};
+
+pub(crate) const COMMENTS: &str = indoc! {r##"
+    # Leading comment
+    key_1: 123 # Trailing comment
+    obj: # On a collection
+      # Indented less than the next line
+        nested: "# not a comment" #no space
+    # Before a dedent
+    arr:
+          # Indented more than the next line
+      - 1
+    # At the end
+"##};
+
+pub(crate) const COMMENT_IN_RAW_AND_BLOCK_STRINGS: &str = indoc! {r##"
+    raw: '# not a comment'
+    block: |
+      # not a comment
+"##};
+
+pub(crate) const COMMENT_AFTER_BLOCK_STRING_HEADER: &str = indoc! {r##"
+    a: | # note
+      x
+    b: >- #tight
+      y
+"##};
+
+pub(crate) const OBJECT_ORDER: &str = indoc! {r#"
+    zebra: 1
+    apple:
+      mango: 2
+      banana: 3
+      cherry: 4
+    kiwi: 5
+    apple_2: 6
+"#};
+
+pub(crate) const INTEGERS_AND_FLOATS: &str = indoc! {r#"
+    int: 1
+    float: 1.0
+    negative: -42
+    big: 9007199254740993
+    max: 9223372036854775807
+    min: -9223372036854775808
+"#};
+
+pub(crate) const DATES_AND_TIMES: &str = indoc! {r#"
+    dates: [2024-01-01, 12:00:00]
+    times:
+      - 12:30:00
+"#};
+
+pub(crate) const FLOW_COLLECTIONS: &str = indoc! {r#"
+    ports: [80, 443,]
+    server: { host: "x", port: 1 } # comment
+    matrix: [[1, 2], [ 3 ], []]
+    mixed: [{ a: 1, b: [true] }, 'raw', null, {}]
+    items:
+      - [1]
+      - { a: 1 }
+"#};
+
+pub(crate) const FLOW_COLLECTIONS_BLOCK: &str = indoc! {r#"
+    ports:
+      - 80
+      - 443
+    server:
+      host: "x"
+      port: 1
+    matrix:
+      -
+        - 1
+        - 2
+      - - 3
+      - []
+    mixed:
+      - a: 1
+        b:
+          - true
+      - 'raw'
+      - null
+      - {}
+    items:
+      - - 1
+      - a: 1
+"#};
+
+// Documents mixing the values of the parser's tests, for the emitter to write back.
+
+pub(crate) const KEYS: &str = indoc! {r#"
+    kebab-case: 1
+    snake_case   : 2
+    spaced  out key: 3
+    -leading-dash: 4
+    123: 5
+    flow: { inner key : 6 }
+    ключ: 1
+    café au lait: 3
+    "x-request-id": 1
+    "a.b/c: d\n": 2
+    'C:\path': 3
+    "": 4
+    "\u{1F600}" : 5
+    "- item": 6
+"#};
+
+pub(crate) const DOTTED: &str = indoc! {r#"
+    database.pool.max: 10
+    database:
+      host: "x"
+      pool:
+        min: 1
+    server . port: 80
+    "a.b".c: 1
+    flow: { a.b: 1, a.c: 2 }
+"#};
+
+pub(crate) const STRINGS: &str = indoc! {r#"
+    path: 'C:\Users\odin'
+    regex: '^\d+\.\d+$'
+    quotes: '"double" quotes'
+    escapes: "tab\tquote\"back\\slash\u{7f}"
+    empty: ''
+    sql: |
+      SELECT *
+      FROM users
+        WHERE id = 1
+    folded: >
+        a long
+        line
+
+        another paragraph
+    kept: |+
+      text
+
+
+    stripped: |-
+      text
+"#};
+
+pub(crate) const NUMBERS: &str = indoc! {r#"
+    int: 1
+    float: 1.0
+    negative: -42
+    small: 0.000001
+    big: 9007199254740993
+    max: 9223372036854775807
+    min: -9223372036854775808
+    hex: 0xff
+    bool: true
+"#};
+
+pub(crate) const TIMES_AND_SIZES: &str = indoc! {r#"
+    date: 2024-02-29
+    time: 23:59:60.5
+    utc: 2024-02-29T09:30:00Z
+    offset: 2024-02-29T09:30:00.000000001-05:30
+    local: 2024-02-29T09:30:00
+    dates: [2024-01-01, 12:00:00]
+    timeout: 1h30m
+    precise: 1.5s
+    tiny: 250us
+    size: 1.5GiB
+    round: 512KiB
+    zero: 0B
+"#};
+
+pub(crate) const FLOW: &str = indoc! {r#"
+    ports: [80, 443,]
+    server: { host: "x", port: 1 } # comment
+    matrix: [[1, 2], [ 3 ], []]
+    mixed: [{ a: 1, b: [true] }, 'raw', null, {}]
+    items:
+      - [1]
+      - { a: 1 }
+    long: [1111111111, 2222222222, 3333333333, 4444444444, 5555555555, 6666666666, 7777777777]
+"#};
+
+pub(crate) const EMPTY: &str = "";
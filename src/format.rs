@@ -0,0 +1,520 @@
+use crate::document::{Document, Kind};
+use crate::{emit, Error, PathSegment, Value};
+
+/// How many spaces each nested block is indented past the line it's under.
+const INDENT: usize = 2;
+
+/// Formats a document in the canonical layout, keeping its comments. Nested blocks are indented
+/// by 2, keys are followed by `:` and a single space, and keys that don't need quotes lose them.
+/// Strings are double quoted, except raw strings whose text would need escapes. Runs of blank
+/// lines are collapsed into one, and top-level entries with nested blocks are separated from the
+/// others by a blank line.
+///
+/// Numbers, dates, durations and the like are kept as they're written, and so is the choice
+/// between flow and block collections. Formatting formatted text doesn't change it.
+pub fn format(input: &str) -> Result<String, Error> {
+    let document = Document::parse(input)?;
+    let mut formatter = Formatter {
+        document: &document,
+        text: document.as_str(),
+        out: String::with_capacity(input.len()),
+        cursor: 0,
+        levels: Vec::new(),
+        last_levels: Vec::new(),
+        keep: false,
+    };
+
+    // An empty document is only comments.
+    let empty = document.nodes().is_empty();
+    if !empty {
+        formatter.block(0, 0, false);
+    }
+    formatter.gap(None, empty, false);
+    Ok(formatter.out)
+}
+
+/// Whether the input is formatted already, so [`format`] would return it unchanged.
+pub fn is_formatted(input: &str) -> Result<bool, Error> {
+    Ok(format(input)? == input)
+}
+
+struct Formatter<'a> {
+    document: &'a Document,
+    text: &'a str,
+    out: String,
+    /// How much of the input has been formatted.
+    cursor: usize,
+    /// The indentation of each block being formatted in the input and the output, outermost
+    /// first.
+    levels: Vec<(usize, usize)>,
+    /// The levels around the last entry formatted, which comments after it can be indented to.
+    last_levels: Vec<(usize, usize)>,
+    /// Whether the last entry ended with a block string keeping its trailing line breaks, which
+    /// a blank line after it would add to.
+    keep: bool,
+}
+
+impl Formatter<'_> {
+    /// Formats the block whose first entry is the node `first`, indented by `indent`. The first
+    /// entry of a compact block follows the `-` it's on.
+    fn block(&mut self, first: usize, indent: usize, compact: bool) {
+        let column = self.document.column(self.document.nodes()[first].start);
+        self.levels.push((column, indent));
+
+        let mut previous = None;
+        let mut next = Some(first);
+        while let Some(index) = next {
+            if !(compact && previous.is_none()) {
+                let separate = self.document.nodes()[index].depth == 0
+                    && previous.is_some_and(|previous| {
+                        self.is_section(previous) || self.is_section(index)
+                    });
+                self.gap(Some(index), previous.is_none(), separate);
+                self.out.push_str(&" ".repeat(indent));
+            }
+
+            self.entry(index, indent);
+            previous = Some(index);
+            next = self.document.next_sibling(index);
+        }
+
+        self.levels.pop();
+    }
+
+    /// Formats the blank lines and comments between the cursor and the line of the node `next`,
+    /// or the end of the input. Blank lines at the start of a block are dropped, and `separate`
+    /// adds one if there isn't one already.
+    fn gap(&mut self, next: Option<usize>, first: bool, separate: bool) {
+        let end = match next {
+            Some(next) => self.document.line_start(self.document.nodes()[next].start),
+            None => self.text.len(),
+        };
+        let gap = &self.text[self.cursor.min(end)..end];
+        self.cursor = end;
+
+        // The blank line separating sections goes before the comments on the next entry, rather
+        // than before those indented under the entry before it.
+        let mut separate = separate && !self.keep;
+        let floor = self.levels.last().map_or(0, |&(_, indent)| indent);
+        let mut blank = false;
+        let mut written = false;
+        for line in gap.lines() {
+            let comment = line.trim();
+            if comment.is_empty() {
+                blank |= written || !first;
+                continue;
+            }
+
+            let column = line.len() - line.trim_start_matches(' ').len();
+            let indent = self.comment_indent(column);
+            if blank || (separate && indent == floor) {
+                self.out.push('\n');
+                blank = false;
+            }
+            if indent == floor {
+                separate = false;
+            }
+            self.out.push_str(&" ".repeat(indent));
+            self.out.push_str(comment);
+            self.out.push('\n');
+            written = true;
+        }
+
+        if (blank || separate) && next.is_some() {
+            self.out.push('\n');
+        }
+    }
+
+    /// The indentation of a comment on its own line, indented by `column` in the input. It's
+    /// indented like the block it lines up with, or the innermost block it's indented past, but
+    /// never less than the block being formatted.
+    fn comment_indent(&self, column: usize) -> usize {
+        let floor = self.levels.last().copied().unwrap_or((0, 0));
+
+        self.last_levels
+            .iter()
+            .chain(Some(&floor))
+            .filter(|(level, _)| *level >= floor.0 && *level <= column)
+            .max_by_key(|(level, _)| *level)
+            .map_or(floor.1, |(_, indent)| *indent)
+    }
+
+    /// Formats an entry or block array item, from its key or `-` to the end of its value.
+    fn entry(&mut self, index: usize, indent: usize) {
+        let document = self.document;
+        let node = &document.nodes()[index];
+        self.last_levels = self.levels.clone();
+        self.keep = false;
+
+        match node.path.last() {
+            Some(PathSegment::Index(_)) => self.out.push('-'),
+            _ => {
+                let key = self.key(index);
+                self.out.push_str(&key);
+                self.out.push(':');
+            }
+        }
+
+        match document.kind(index) {
+            Kind::Empty => self.end_of_line(node.slot),
+            Kind::Inline => {
+                let end = node.value_end.unwrap_or(node.value);
+                if self.text[node.value..].starts_with(&['|', '>'][..]) {
+                    self.block_string(node.value, end, indent + INDENT);
+                } else {
+                    self.out.push(' ');
+                    let value = self.value(index);
+                    self.out.push_str(&value);
+                    self.end_of_line(end);
+                }
+            }
+            Kind::Nested => {
+                self.end_of_line(node.slot);
+                self.block(index + 1, indent + INDENT, false);
+            }
+            Kind::Compact => {
+                self.out.push(' ');
+                self.block(index + 1, indent + INDENT, true);
+            }
+        }
+    }
+
+    /// Ends the line with the comment after `offset`, if there is one.
+    fn end_of_line(&mut self, offset: usize) {
+        let rest = self.document.rest_of_line(offset).trim();
+        if rest.starts_with('#') {
+            self.out.push(' ');
+            self.out.push_str(rest);
+        }
+        self.out.push('\n');
+        self.cursor = self.document.line_end(offset);
+    }
+
+    /// A block string from its header at `start` to the end of its last line at `end`, with its
+    /// lines indented by `indent`. The header is `|` or `>` and an optional chomping indicator,
+    /// which can be followed by a comment.
+    fn block_string(&mut self, start: usize, end: usize, indent: usize) {
+        let header_len = if self.text[start + 1..].starts_with(&['+', '-'][..]) {
+            2
+        } else {
+            1
+        };
+        let header = &self.text[start..start + header_len];
+        self.out.push(' ');
+        self.out.push_str(header);
+        self.end_of_line(start + header_len);
+        let header_end = self.cursor;
+
+        // The block ends with the blank lines after its last line. They're only part of the
+        // string with `+`, and otherwise are left to the gap after the entry. A block of only
+        // blank lines ends at the start of its last one, which can be where the header ends.
+        let after_header = self.text[..header_end].ends_with('\n');
+        let mut lines: Vec<&str> = if end >= header_end && after_header {
+            self.text[header_end..end].split('\n').collect()
+        } else {
+            Vec::new()
+        };
+        self.keep = header.ends_with('+');
+        if !self.keep {
+            while lines.last().is_some_and(|line| is_blank(line)) {
+                lines.pop();
+            }
+        }
+
+        let block_indent = lines
+            .iter()
+            .find(|line| !is_blank(line))
+            .map_or(0, |line| line.len() - line.trim_start_matches(' ').len());
+        for line in &lines {
+            if !is_blank(line) {
+                self.out.push_str(&" ".repeat(indent));
+                self.out.push_str(&line[block_indent..]);
+            }
+            self.out.push('\n');
+        }
+
+        let consumed: usize = lines.iter().map(|line| line.len() + 1).sum();
+        self.cursor = (header_end + consumed).min(self.text.len());
+    }
+
+    /// The text of a value on the line of its key or `-`, or in a flow collection.
+    fn value(&self, index: usize) -> String {
+        let nodes = self.document.nodes();
+        let node = &nodes[index];
+        let token = &self.text[node.value..node.value_end.unwrap_or(node.value)];
+
+        if token.starts_with('[') || token.starts_with('{') {
+            let array = token.starts_with('[');
+            let mut items = Vec::new();
+            let mut next = Some(index + 1).filter(|&next| {
+                nodes
+                    .get(next)
+                    .is_some_and(|child| child.depth > node.depth)
+            });
+
+            while let Some(child) = next {
+                let value = self.value(child);
+                items.push(if array {
+                    value
+                } else {
+                    format!("{}: {}", self.key(child), value)
+                });
+                next = self.document.next_sibling(child);
+            }
+
+            return match (array, items.is_empty()) {
+                (true, _) => format!("[{}]", items.join(", ")),
+                (false, true) => "{}".to_string(),
+                (false, false) => format!("{{ {} }}", items.join(", ")),
+            };
+        }
+
+        match self.document.get(&node.path) {
+            Some(Value::String(string)) if !token.starts_with(&['|', '>'][..]) => {
+                quote(token, string)
+            }
+            Some(Value::Null) => "null".to_string(),
+            _ => token.to_string(),
+        }
+    }
+
+    /// The key of the node `index`, dotted if it's written that way.
+    fn key(&self, index: usize) -> String {
+        let node = &self.document.nodes()[index];
+        let written = &self.text[node.start..node.slot];
+
+        node.path[node.depth..]
+            .iter()
+            .map(|segment| match segment {
+                PathSegment::Key(key) if emit::is_bare_key(key) => key.to_string(),
+                PathSegment::Key(key) => quote(written, key),
+                PathSegment::Index(index) => index.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    fn is_section(&self, index: usize) -> bool {
+        matches!(self.document.kind(index), Kind::Nested | Kind::Compact)
+    }
+}
+
+/// `string` double quoted, unless it was `written` as a raw string and double quoting it would
+/// need escapes.
+fn quote(written: &str, string: &str) -> String {
+    let raw = format!("'{}'", string);
+    if (string.contains('\\') || string.contains('"')) && written.contains(&raw) {
+        raw
+    } else {
+        emit::quote(string)
+    }
+}
+
+/// Whether a line of a block string is blank, which only spaces can make it.
+fn is_blank(line: &str) -> bool {
+    line.trim_start_matches(' ').is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    use indoc::indoc;
+
+    const MESSY: &str = indoc! {r#"
+
+
+        # Service settings
+        name:    "demo"   # the name
+        "version": '1.2'
+        raw: 'C:\path'
+        nothing: ~
+
+
+        server:
+            host:   "localhost"
+              # about the port
+            port: 8080
+
+            tls:   # optional
+                enabled: true
+        # databases
+        database.pool.min: 1
+        flags: [ 'a' ,"b",  ]
+        limits: {cpu: 2,mem: 512MiB, "max-files": 10}
+        servers:
+          - name: "a"
+            port: 1
+          -
+              name: "b"
+        text: |
+              first
+                indented
+
+              last
+        kept: |+  # keep these
+          x
+
+
+        tail: 1   
+            # trailing
+
+
+    "#};
+
+    const FORMATTED: &str = indoc! {r#"
+        # Service settings
+        name: "demo" # the name
+        version: "1.2"
+        raw: 'C:\path'
+        nothing: null
+
+        server:
+          host: "localhost"
+          # about the port
+          port: 8080
+
+          tls: # optional
+            enabled: true
+
+        # databases
+        database.pool.min: 1
+        flags: ["a", "b"]
+        limits: { cpu: 2, mem: 512MiB, max-files: 10 }
+
+        servers:
+          - name: "a"
+            port: 1
+          -
+            name: "b"
+
+        text: |
+          first
+            indented
+
+          last
+        kept: |+ # keep these
+          x
+
+
+        tail: 1
+        # trailing
+    "#};
+
+    #[test]
+    fn canonical_layout() {
+        assert_eq!(format(MESSY).unwrap(), FORMATTED);
+        assert_eq!(parse(FORMATTED), parse(MESSY));
+    }
+
+    #[test]
+    fn sections() {
+        let input = indoc! {r#"
+            a: 1
+            b:
+              c: 2
+            d: 3
+
+
+            e: 4
+            f:
+              - 5
+            g:
+              - h: 6
+        "#};
+        let expected = indoc! {r#"
+            a: 1
+
+            b:
+              c: 2
+
+            d: 3
+
+            e: 4
+
+            f:
+              - 5
+
+            g:
+              - h: 6
+        "#};
+        assert_eq!(format(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn comments() {
+        let input = indoc! {r#"
+            a:
+              b:
+                c: 1
+                # under c
+              # under b
+            # under a
+             # between a and b
+            d: 2 #  after d    
+        "#};
+        let expected = indoc! {r#"
+            a:
+              b:
+                c: 1
+                # under c
+              # under b
+
+            # under a
+            # between a and b
+            d: 2 #  after d
+        "#};
+        assert_eq!(format(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn only_comments() {
+        assert_eq!(format("").unwrap(), "");
+        assert_eq!(format("\n  \n").unwrap(), "");
+        assert_eq!(
+            format("\n# a: 1\n\n\n  # b: 2").unwrap(),
+            "# a: 1\n\n# b: 2\n"
+        );
+        assert!(is_formatted("# a: 1\n").unwrap());
+    }
+
+    #[test]
+    fn idempotent() {
+        let fixtures = [
+            MESSY,
+            indoc! {r#"
+                - - 1
+                  - [2, { b: 3 }]
+                -
+                    key: |
+                      text
+                - { a.b: 1, "c d": [] }
+                -
+            "#},
+            "a:\n    b:\n        - c: 1\n          d:\n            - 2",
+            "a: \"it's\"\nb: 'say \"hi\"'\nc: \"tab\\there\"\n",
+            // Blocks of only blank lines, which are the string with `+`.
+            "a: |+\n\n",
+            "a: |+\n\n\nb: 1\n",
+            "- |+\n    ",
+            "- >+\n\n- |\n\n- 1\n",
+        ];
+
+        for fixture in fixtures.iter().chain(crate::fixtures::DOCUMENTS) {
+            let formatted = format(fixture).unwrap();
+            assert_eq!(parse(&formatted), parse(fixture), "{}", formatted);
+            assert_eq!(format(&formatted).unwrap(), formatted);
+            assert!(is_formatted(&formatted).unwrap());
+        }
+    }
+
+    #[test]
+    fn check_mode() {
+        assert!(is_formatted(FORMATTED).unwrap());
+        assert!(!is_formatted(MESSY).unwrap());
+        assert!(!is_formatted("a:  1\n").unwrap());
+        assert!(!is_formatted("a: 1").unwrap());
+        assert!(is_formatted("a: 1\n").unwrap());
+        assert!(is_formatted("a: :").is_err());
+    }
+}
@@ -1,337 +1,3296 @@
-// TODO: Comments
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::time::{Duration, SystemTime};
+
+use indexmap::IndexMap;
+use nom::{
+    branch::alt,
+    bytes::complete::{is_not, tag, take_while, take_while1, take_while_m_n},
+    character::complete::{char, not_line_ending, satisfy, space0, space1},
+    combinator::{cut, eof, map, map_opt, opt, peek, recognize},
+    multi::{fold_many0, many0},
+    sequence::{delimited, pair, preceded, terminated},
+};
+
+mod datetime;
+#[cfg(feature = "serde")]
+mod de;
+mod diagnostic;
+mod document;
+mod emit;
+mod error;
+#[cfg(test)]
+mod fixtures;
+mod format;
+#[cfg(feature = "serde")]
+mod ser;
+
+pub use datetime::{Date, DateTime, Offset, Time};
+#[cfg(feature = "serde")]
+pub use de::{from_str, from_value};
+pub use diagnostic::Diagnostic;
+pub use document::Document;
+pub use emit::{emit, EmitOptions, FloatFormat, KeyOrder, QuoteStyle};
+pub use error::{ByteSizeError, ConversionError, Error, NumberError, Position};
+pub use format::{format, is_formatted};
+#[cfg(feature = "serde")]
+pub use ser::{to_string, to_value};
+
+use error::{ErrorKind, ParseError};
+
+type IResult<'a, O> = nom::IResult<&'a str, O, ParseError<'a>>;
+
+/// How deeply collections can be nested. The parsers are recursive, so this stops deeply nested
+/// input from overflowing the stack.
+const MAX_DEPTH: usize = 128;
+
+/// The map used for objects, which keeps keys in the order they appear in the input.
+pub type Map<'a> = IndexMap<Cow<'a, str>, Value<'a>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value<'a> {
+    /// `null` or `~`, or a key or array item with nothing after it.
+    Null,
+    String(Cow<'a, str>),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    DateTime(DateTime),
+    Date(Date),
+    Time(Time),
+    /// A length of time like `1h30m`.
+    Duration(Duration),
+    /// A number of bytes, written like `512KiB`.
+    Bytes(u64),
+    Object(Map<'a>),
+    Array(Vec<Value<'a>>),
+}
+
+impl Value<'_> {
+    /// The value as an `i64`, if it's an integer or a float with no fractional part that's in
+    /// range.
+    pub fn as_i64(&self) -> Result<i64, ConversionError> {
+        match *self {
+            Value::Integer(integer) => Ok(integer),
+            Value::Float(float)
+                if float.fract() == 0.0 && float >= -(2f64.powi(63)) && float < 2f64.powi(63) =>
+            {
+                Ok(float as i64)
+            }
+            Value::Float(_) => Err(ConversionError::Lossy("i64")),
+            _ => Err(ConversionError::NotANumber),
+        }
+    }
+
+    /// The value as a `u64`, if it's a non-negative integer or a float with no fractional part
+    /// that's in range.
+    pub fn as_u64(&self) -> Result<u64, ConversionError> {
+        match *self {
+            Value::Integer(integer) => {
+                u64::try_from(integer).map_err(|_| ConversionError::Lossy("u64"))
+            }
+            Value::Float(float)
+                if float.fract() == 0.0 && float >= 0.0 && float < 2f64.powi(64) =>
+            {
+                Ok(float as u64)
+            }
+            Value::Float(_) => Err(ConversionError::Lossy("u64")),
+            _ => Err(ConversionError::NotANumber),
+        }
+    }
+
+    /// The value as an `f64`, if it's a float or an integer that an `f64` can represent exactly.
+    pub fn as_f64(&self) -> Result<f64, ConversionError> {
+        match *self {
+            Value::Float(float) => Ok(float),
+            Value::Integer(integer) => {
+                let float = integer as f64;
+                // `as` saturates, so check the range before converting back.
+                if float < 2f64.powi(63) && float as i64 == integer {
+                    Ok(float)
+                } else {
+                    Err(ConversionError::Lossy("f64"))
+                }
+            }
+            _ => Err(ConversionError::NotANumber),
+        }
+    }
+
+    pub fn as_byte_size(&self) -> Result<u64, ConversionError> {
+        match *self {
+            Value::Bytes(bytes) => Ok(bytes),
+            _ => Err(ConversionError::NotAByteSize),
+        }
+    }
+
+    pub fn as_duration(&self) -> Result<Duration, ConversionError> {
+        match *self {
+            Value::Duration(duration) => Ok(duration),
+            _ => Err(ConversionError::NotADuration),
+        }
+    }
+
+    /// The value as a point in time, if it's a date-time with an offset.
+    pub fn as_system_time(&self) -> Result<SystemTime, ConversionError> {
+        match self {
+            Value::DateTime(DateTime { offset: None, .. }) => Err(ConversionError::NoOffset),
+            Value::DateTime(date_time) => date_time
+                .to_system_time()
+                .ok_or(ConversionError::OutOfRange("SystemTime")),
+            _ => Err(ConversionError::NotADateTime),
+        }
+    }
+}
+
+/// A step along the path from the root value to a nested value.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathSegment<'a> {
+    Key(Cow<'a, str>),
+    Index(usize),
+}
+
+pub type Path<'a> = Vec<PathSegment<'a>>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CommentKind {
+    /// A comment on its own line before the value.
+    Leading,
+    /// A comment at the end of the value's line. For collections this is the line with the key or
+    /// `-`.
+    Trailing,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment<'a> {
+    /// Everything following the `#` up to the end of the line.
+    pub text: &'a str,
+    pub kind: CommentKind,
+}
+
+impl<'a> Comment<'a> {
+    fn new(text: &'a str, kind: CommentKind) -> Self {
+        Self { text, kind }
+    }
+}
+
+/// Comments from the input, keyed by the path of the value they're attached to. Comments after
+/// the last value in the input are attached to the root (the empty path).
+#[derive(Debug, Default, PartialEq)]
+pub struct Comments<'a> {
+    comments: HashMap<Path<'a>, Vec<Comment<'a>>>,
+}
+
+impl<'a> Comments<'a> {
+    /// The comments attached to the value at `path`, in the order they appear in the input.
+    pub fn get(&self, path: &[PathSegment<'a>]) -> &[Comment<'a>] {
+        self.comments.get(path).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Path<'a>, &[Comment<'a>])> {
+        self.comments.iter().map(|(path, c)| (path, c.as_slice()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.comments.is_empty()
+    }
+
+    fn push(&mut self, path: Path<'a>, comment: Comment<'a>) {
+        self.comments.entry(path).or_default().push(comment);
+    }
+}
+
+/// What to do when a key appears more than once in the same object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeys {
+    /// Fail with [`Error::DuplicateKey`], the default.
+    #[default]
+    Error,
+    /// Keep the value from the first appearance of the key.
+    KeepFirst,
+    /// Keep the value from the last appearance of the key.
+    KeepLast,
+}
+
+/// Options to change how input is parsed. [`parse`] uses the defaults.
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    duplicate_keys: DuplicateKeys,
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn duplicate_keys(mut self, duplicate_keys: DuplicateKeys) -> Self {
+        self.duplicate_keys = duplicate_keys;
+        self
+    }
+
+    pub fn parse<'a>(&self, input: &'a str) -> Result<Value<'a>, Error> {
+        self.parse_with_comments(input).map(|(value, _)| value)
+    }
+
+    /// Parses the input like [`ParseOptions::parse`], also returning the comments found along the
+    /// way.
+    pub fn parse_with_comments<'a>(
+        &self,
+        input: &'a str,
+    ) -> Result<(Value<'a>, Comments<'a>), Error> {
+        let ctx = Context {
+            options: self.clone(),
+            ..Context::default()
+        };
+
+        document(input, ctx)
+            .map(|(_, (value, ctx))| (value, ctx.comments))
+            .map_err(|e| Error::new(input, e))
+    }
+}
+
+/// State threaded through the block level parsers.
+#[derive(Default)]
+struct Context<'a> {
+    options: ParseOptions,
+    /// The path to the value being parsed.
+    path: Path<'a>,
+    /// Comments on their own line, waiting for the next value to be attached to.
+    pending_comments: Vec<&'a str>,
+    comments: Comments<'a>,
+    /// Where each key in the document was defined, by its path, for finding duplicate and
+    /// conflicting keys.
+    keys: HashMap<Path<'a>, KeyDefinition<'a>>,
+    /// Where each value starts, by its path, if they're being recorded.
+    spans: Option<Spans<'a>>,
+    /// How each entry and array item is written, if it's being recorded.
+    syntax: Option<Syntax<'a>>,
+}
+
+/// The input at the start of each value, by its path.
+type Spans<'a> = HashMap<Path<'a>, &'a str>;
+
+/// The entries of objects and items of arrays, in the order they start in the input.
+#[derive(Default)]
+struct Syntax<'a> {
+    nodes: Vec<SyntaxNode<'a>>,
+    /// The nodes being parsed, innermost last.
+    open: Vec<usize>,
+}
+
+/// Where an entry or array item is in the input.
+struct SyntaxNode<'a> {
+    /// The path of the value. For dotted keys this is nested in the objects they create.
+    path: Path<'a>,
+    /// How many segments of the path are of the collection the entry is written in.
+    depth: usize,
+    /// Whether the entry is in a flow collection.
+    flow: bool,
+    /// The input at the start of the key, or of the `-` or value of an array item.
+    start: &'a str,
+    /// The input after the `:` or `-`, or at the value of a flow array item.
+    slot: &'a str,
+    /// The input at the start of the value, which is at `slot` when there's no value.
+    value: &'a str,
+    /// The input after the value, for values that start on the entry's line and aren't block
+    /// collections.
+    value_end: Option<&'a str>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct KeyDefinition<'a> {
+    /// The input at the start of the entry defining the key.
+    input: &'a str,
+    kind: KeyKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum KeyKind {
+    /// An object created by a dotted key, which an explicit object can be merged into.
+    Dotted,
+    Object,
+    /// Anything other than an object.
+    Value,
+}
+
+impl<'a> Context<'a> {
+    /// Starts parsing the value at `segment`, which any pending comments lead up to.
+    fn enter(&mut self, segment: PathSegment<'a>) {
+        self.path.push(segment);
+        for text in std::mem::take(&mut self.pending_comments) {
+            self.comment(text, CommentKind::Leading);
+        }
+    }
+
+    fn exit(&mut self) {
+        self.path.pop();
+    }
+
+    /// Starts parsing the value of a possibly dotted key.
+    fn enter_key(&mut self, key: &DottedKey<'a>) {
+        self.path
+            .extend(key.parents.iter().cloned().map(PathSegment::Key));
+        self.enter(PathSegment::Key(key.key.clone()));
+    }
+
+    fn exit_key(&mut self, key: &DottedKey<'a>) {
+        self.path.truncate(self.path.len() - key.len());
+    }
+
+    /// Fails early on a key that's already been defined when duplicates are errors, rather than
+    /// after parsing its value. `input` is the start of the entry.
+    fn check_duplicate(
+        &self,
+        input: &'a str,
+        key: &DottedKey<'a>,
+    ) -> Result<(), nom::Err<ParseError<'a>>> {
+        let mut path = self.path.clone();
+        path.extend(key.segments().cloned().map(PathSegment::Key));
+
+        match self.keys.get(&path) {
+            Some(first)
+                if first.kind != KeyKind::Dotted
+                    && self.options.duplicate_keys == DuplicateKeys::Error =>
+            {
+                Err(duplicate_key(input, key.join(key.len()), first.input))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Adds the entry at `input` to `object`, the object at the current path. The parent keys of a
+    /// dotted key are objects which are created if they don't exist already. Keys defined more
+    /// than once are handled the way the options say, and defining a key as both an object and
+    /// something else is an error.
+    fn insert(
+        &mut self,
+        object: &mut Map<'a>,
+        input: &'a str,
+        key: DottedKey<'a>,
+        value: Value<'a>,
+    ) -> Result<(), nom::Err<ParseError<'a>>> {
+        let mut object = object;
+        let mut path = self.path.clone();
+
+        for (i, parent) in key.parents.iter().enumerate() {
+            path.push(PathSegment::Key(parent.clone()));
+            match self.keys.get(&path) {
+                None => {
+                    self.keys.insert(
+                        path.clone(),
+                        KeyDefinition {
+                            input,
+                            kind: KeyKind::Dotted,
+                        },
+                    );
+                    self.span_at(path.clone(), input);
+                }
+                Some(first) if first.kind == KeyKind::Value => {
+                    return Err(conflicting_key(input, key.join(i + 1), first.input));
+                }
+                Some(_) => {}
+            }
+
+            // The object might be missing when adding to an object defined outside of this one,
+            // which this one is merged into later.
+            object = match object
+                .entry(parent.clone())
+                .or_insert_with(|| Value::Object(Map::new()))
+            {
+                Value::Object(nested) => nested,
+                _ => return Err(conflicting_key(input, key.join(i + 1), input)),
+            };
+        }
+
+        path.push(PathSegment::Key(key.key.clone()));
+        let kind = match value {
+            Value::Object(_) => KeyKind::Object,
+            _ => KeyKind::Value,
+        };
+        let definition = KeyDefinition { input, kind };
+
+        match self.keys.get(&path).copied() {
+            None => {
+                object.insert(key.key, value);
+                self.keys.insert(path, definition);
+            }
+            Some(first) if first.kind == KeyKind::Dotted => {
+                let value = match (object.get_mut(&key.key), value) {
+                    (Some(Value::Object(existing)), Value::Object(new)) => {
+                        merge(existing, new);
+                        None
+                    }
+                    (None, value @ Value::Object(_)) => Some(value),
+                    _ => return Err(conflicting_key(input, key.join(key.len()), first.input)),
+                };
+                if let Some(value) = value {
+                    object.insert(key.key, value);
+                }
+                self.keys.insert(path, definition);
+            }
+            Some(first) => match self.options.duplicate_keys {
+                DuplicateKeys::Error => {
+                    return Err(duplicate_key(input, key.join(key.len()), first.input));
+                }
+                DuplicateKeys::KeepFirst => {
+                    // Forget the keys nested in the value being thrown away.
+                    self.forget(&path, |nested| nested.input.len() <= input.len());
+                }
+                DuplicateKeys::KeepLast => {
+                    // Forget the keys nested in the value being replaced.
+                    self.forget(&path, |nested| nested.input.len() > input.len());
+                    object.insert(key.key, value);
+                    self.keys.insert(path, definition);
+                }
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Forgets the definitions of the keys nested under `path` that `predicate` is true for.
+    fn forget(&mut self, path: &[PathSegment<'a>], predicate: impl Fn(&KeyDefinition<'a>) -> bool) {
+        self.keys.retain(|nested, definition| {
+            !(nested.len() > path.len() && nested.starts_with(path) && predicate(definition))
+        });
+    }
+
+    /// Records that the value at the current path starts at `input`.
+    fn span(&mut self, input: &'a str) {
+        if self.spans.is_some() {
+            self.span_at(self.path.clone(), input);
+        }
+        if let Some(node) = self.open_node() {
+            node.value = input;
+        }
+    }
+
+    /// Records that the value at the current path, which started on the line of its key or `-`,
+    /// ends at `input`.
+    fn span_end(&mut self, input: &'a str) {
+        if let Some(node) = self.open_node() {
+            node.value_end = Some(input);
+        }
+    }
+
+    /// Starts recording the syntax of the entry or array item at the current path, whose key
+    /// is `len` segments long.
+    fn start_node(&mut self, len: usize, flow: bool, start: &'a str, slot: &'a str) {
+        if let Some(syntax) = &mut self.syntax {
+            syntax.open.push(syntax.nodes.len());
+            syntax.nodes.push(SyntaxNode {
+                path: self.path.clone(),
+                depth: self.path.len() - len,
+                flow,
+                start,
+                slot,
+                value: slot,
+                value_end: None,
+            });
+        }
+    }
+
+    fn end_node(&mut self) {
+        if let Some(syntax) = &mut self.syntax {
+            syntax.open.pop();
+        }
+    }
+
+    /// The node being recorded for the value at the current path.
+    fn open_node(&mut self) -> Option<&mut SyntaxNode<'a>> {
+        let path = &self.path;
+        let syntax = self.syntax.as_mut()?;
+        let node = &mut syntax.nodes[*syntax.open.last()?];
+        Some(node).filter(|node| node.path == *path)
+    }
+
+    fn span_at(&mut self, path: Path<'a>, input: &'a str) {
+        let keep_first = self.options.duplicate_keys == DuplicateKeys::KeepFirst;
+
+        if let Some(spans) = &mut self.spans {
+            let span = spans.entry(path).or_insert(input);
+            // Otherwise the later value replaces the earlier one.
+            if !keep_first {
+                *span = input;
+            }
+        }
+    }
+
+    fn comment(&mut self, text: &'a str, kind: CommentKind) {
+        self.comments
+            .push(self.path.clone(), Comment::new(text, kind));
+    }
+}
+
+fn null(input: &str) -> IResult<'_, &str> {
+    alt((tag("null"), tag("~")))(input)
+}
+
+fn boolean(input: &str) -> IResult<'_, bool> {
+    use nom::combinator::value as v;
+
+    alt((v(true, tag("true")), v(false, tag("false"))))(input)
+}
+
+/// A date, time or date-time in the format of RFC 3339:
+///
+/// ```text
+/// date-time = date ("T" | "t" | " ") time offset?
+/// date      = [0-9]{4} "-" [0-9]{2} "-" [0-9]{2}
+/// time      = [0-9]{2} ":" [0-9]{2} ":" [0-9]{2} ("." [0-9]{1,9})?
+/// offset    = "Z" | "z" | ("+" | "-") [0-9]{2} ":" [0-9]{2}
+/// ```
+///
+/// A date-time without an offset is a local date-time. An offset of `-00:00`, which RFC 3339 uses
+/// for a UTC time whose local offset is unknown, is an error rather than being read as `Z`.
+///
+/// Nothing else starts with four digits and a `-`, or two digits and a `:`, so from there anything
+/// that doesn't match is an error.
+fn date_time(input: &str) -> IResult<'_, Value<'_>> {
+    let invalid = || nom::Err::Failure(ParseError::new(input, ErrorKind::InvalidDateTime));
+
+    let (rest, value) = match time(input) {
+        Ok((rest, time)) => (rest, Value::Time(time)),
+        Err(nom::Err::Error(_)) => {
+            let (rest, date) = date(input)?;
+            let time_input = rest.strip_prefix(&['T', 't'][..]).or_else(|| {
+                rest.strip_prefix(' ')
+                    .filter(|t| t.starts_with(|c: char| c.is_ascii_digit()))
+            });
+
+            match time_input {
+                Some(time_input) => {
+                    let (rest, time) = time(time_input).map_err(|_| invalid())?;
+                    let (rest, offset) = offset(rest).map_err(|_| invalid())?;
+                    (rest, Value::DateTime(DateTime { date, time, offset }))
+                }
+                None => (rest, Value::Date(date)),
+            }
+        }
+        Err(e) => return Err(e),
+    };
+
+    if rest.starts_with(|c: char| c.is_alphanumeric() || "_.:+-".contains(c)) {
+        return Err(invalid());
+    }
+
+    Ok((rest, value))
+}
+
+/// Exactly `n` digits.
+fn fixed_digits<'a, T: std::str::FromStr>(n: usize) -> impl FnMut(&'a str) -> IResult<'a, T> {
+    map_opt(
+        take_while_m_n(n, n, |c: char| c.is_ascii_digit()),
+        |digits: &str| digits.parse().ok(),
+    )
+}
+
+fn date(input: &str) -> IResult<'_, Date> {
+    let (rest, year) = terminated(fixed_digits(4), char('-'))(input)?;
+
+    let invalid = || nom::Err::Failure(ParseError::new(input, ErrorKind::InvalidDateTime));
+    let result: IResult<'_, _> =
+        pair(terminated(fixed_digits(2), char('-')), fixed_digits(2))(rest);
+    let (rest, (month, day)) = result.map_err(|_| invalid())?;
+
+    match Date::new(year, month, day) {
+        Some(date) => Ok((rest, date)),
+        None => Err(invalid()),
+    }
+}
+
+fn time(input: &str) -> IResult<'_, Time> {
+    let (rest, hour) = terminated(fixed_digits(2), char(':'))(input)?;
+
+    let invalid = || nom::Err::Failure(ParseError::new(input, ErrorKind::InvalidDateTime));
+    let result: IResult<'_, _> =
+        pair(terminated(fixed_digits(2), char(':')), fixed_digits(2))(rest);
+    let (rest, (minute, second)) = result.map_err(|_| invalid())?;
+
+    let result: IResult<'_, _> = opt(preceded(
+        char('.'),
+        take_while_m_n(1, 9, |c: char| c.is_ascii_digit()),
+    ))(rest);
+    let (rest, fraction) = result.map_err(|_| invalid())?;
+    let nanosecond = match fraction {
+        Some(fraction) => format!("{:0<9}", fraction).parse().map_err(|_| invalid())?,
+        None => 0,
+    };
+
+    match Time::new(hour, minute, second, nanosecond) {
+        Some(time) => Ok((rest, time)),
+        None => Err(invalid()),
+    }
+}
+
+fn offset(input: &str) -> IResult<'_, Option<Offset>> {
+    if let Some(rest) = input.strip_prefix(&['Z', 'z'][..]) {
+        return Ok((rest, Some(Offset::UTC)));
+    }
+
+    let sign = match input.chars().next() {
+        Some('+') => 1,
+        Some('-') => -1,
+        _ => return Ok((input, None)),
+    };
+    let (rest, (hours, minutes)) = pair(
+        terminated(fixed_digits::<i16>(2), char(':')),
+        fixed_digits::<i16>(2),
+    )(&input[1..])?;
+
+    let valid = hours < 24 && minutes < 60 && !(sign < 0 && hours == 0 && minutes == 0);
+    match Offset::new(sign * (hours * 60 + minutes)).filter(|_| valid) {
+        Some(offset) => Ok((rest, Some(offset))),
+        None => Err(nom::Err::Error(ParseError::new(
+            input,
+            ErrorKind::InvalidDateTime,
+        ))),
+    }
+}
+
+/// The units of a duration, with the number of nanoseconds in each, from largest to smallest.
+const DURATION_UNITS: [(&str, u128); 8] = [
+    ("d", 86_400_000_000_000),
+    ("h", 3_600_000_000_000),
+    ("ms", 1_000_000),
+    ("m", 60_000_000_000),
+    ("s", 1_000_000_000),
+    ("us", 1_000),
+    ("µs", 1_000),
+    ("ns", 1),
+];
+
+/// A length of time made of numbers each followed by a unit, largest unit first and each used at
+/// most once, like `1h30m`, `0.5s` or `250ms`. Units are `d`, `h`, `m`, `s`, `ms`, `us` (or `µs`)
+/// and `ns`.
+fn duration(input: &str) -> IResult<'_, Duration> {
+    let invalid = |at| {
+        Err(nom::Err::Failure(ParseError::new(
+            at,
+            ErrorKind::InvalidDuration,
+        )))
+    };
+    let mut rest = input;
+    let mut nanoseconds: u128 = 0;
+    let mut last_unit = None;
+
+    loop {
+        let component = rest;
+        let (after, (whole, fraction)) = decimal(component)?;
+
+        let unit = DURATION_UNITS
+            .iter()
+            .find(|(unit, _)| after.starts_with(unit));
+        let &(unit, unit_nanoseconds) = match unit {
+            Some(unit) => unit,
+            // Without a unit it's just a number.
+            None if last_unit.is_none() => {
+                return Err(nom::Err::Error(ParseError::new(
+                    input,
+                    ErrorKind::Nom(nom::error::ErrorKind::Digit),
+                )))
+            }
+            None => return invalid(component),
+        };
+        // Comparing sizes also catches `us` and `µs` together.
+        if last_unit.is_some_and(|last| unit_nanoseconds >= last) {
+            return invalid(component);
+        }
+        last_unit = Some(unit_nanoseconds);
+
+        nanoseconds = match scale(whole, fraction, unit_nanoseconds) {
+            Some((_, false)) => return invalid(component),
+            Some((scaled, true)) => match nanoseconds.checked_add(scaled) {
+                Some(nanoseconds) => nanoseconds,
+                None => return invalid(input),
+            },
+            None => return invalid(input),
+        };
+
+        rest = &after[unit.len()..];
+        if !rest.starts_with(|c: char| c.is_ascii_digit()) {
+            break;
+        }
+    }
+
+    if rest.starts_with(|c: char| c.is_alphanumeric() || c == '.' || c == '_') {
+        return invalid(input);
+    }
+
+    match u64::try_from(nanoseconds / 1_000_000_000) {
+        Ok(seconds) => Ok((
+            rest,
+            Duration::new(seconds, (nanoseconds % 1_000_000_000) as u32),
+        )),
+        Err(_) => invalid(input),
+    }
+}
+
+/// A number with an optional fraction, like the `1.5` in `1.5h`. Returns the digits before and
+/// after the `.`.
+fn decimal(input: &str) -> IResult<'_, (&str, Option<&str>)> {
+    let (rest, whole) = digits(input, 10)?;
+
+    match rest.strip_prefix('.') {
+        Some(fraction) if fraction.starts_with(|c: char| c.is_ascii_digit()) => {
+            let (rest, fraction) = digits(fraction, 10)?;
+            Ok((rest, (whole, Some(fraction))))
+        }
+        _ => Ok((rest, (whole, None))),
+    }
+}
+
+/// Converts a [`decimal`] number of some unit to a whole number of a smaller unit, like hours to
+/// nanoseconds, where `unit` is the size of the unit in the smaller one. Returns the number and
+/// whether it's exact, or `None` if it overflows.
+fn scale(whole: &str, fraction: Option<&str>, unit: u128) -> Option<(u128, bool)> {
+    let whole = whole
+        .replace('_', "")
+        .parse::<u128>()
+        .ok()?
+        .checked_mul(unit)?;
+    let fraction = match fraction {
+        Some(fraction) => fraction.replace('_', ""),
+        None => return Some((whole, true)),
+    };
+
+    // A fraction too long to parse would be inexact anyway, as `unit` is at most 2^64.
+    let denominator = match 10u128.checked_pow(fraction.len() as u32) {
+        Some(denominator) if fraction.len() < 20 => denominator,
+        _ => return Some((whole, false)),
+    };
+    let numerator = fraction.parse::<u128>().ok()?.checked_mul(unit)?;
+
+    Some((
+        whole.checked_add(numerator / denominator)?,
+        numerator % denominator == 0,
+    ))
+}
+
+/// The units of a byte size, with the number of bytes in each.
+const BYTE_UNITS: [(&str, u128); 14] = [
+    ("B", 1),
+    ("kB", 1_000),
+    ("KB", 1_000),
+    ("MB", 1_000_000),
+    ("GB", 1_000_000_000),
+    ("TB", 1_000_000_000_000),
+    ("PB", 1_000_000_000_000_000),
+    ("EB", 1_000_000_000_000_000_000),
+    ("KiB", 1 << 10),
+    ("MiB", 1 << 20),
+    ("GiB", 1 << 30),
+    ("TiB", 1 << 40),
+    ("PiB", 1 << 50),
+    ("EiB", 1 << 60),
+];
+
+/// A number of bytes written as a number followed by a unit, like `512KiB`, `10MB` or `1.5GiB`.
+/// The number can have a fraction as long as the size is a whole number of bytes, and must fit in
+/// a `u64`. Any other unit ending in `B` or `b`, like `Mb`, is an error rather than being left to
+/// the other parsers.
+fn byte_size(input: &str) -> IResult<'_, u64> {
+    let invalid = |at, reason| {
+        Err(nom::Err::Failure(ParseError::new(
+            at,
+            ErrorKind::InvalidByteSize(reason),
+        )))
+    };
+
+    let not_a_byte_size = Err(nom::Err::Error(ParseError::new(
+        input,
+        ErrorKind::Nom(nom::error::ErrorKind::Alpha),
+    )));
+    // `0b` is the start of a binary number, not zero of some unit.
+    if radix(input).is_some() {
+        return not_a_byte_size;
+    }
+
+    let (unit_input, (whole, fraction)) = decimal(input)?;
+    let (rest, unit) = take_while(|c: char| c.is_ascii_alphabetic())(unit_input)?;
+    if rest.starts_with(|c: char| c.is_alphanumeric() || c == '.' || c == '_') {
+        return not_a_byte_size;
+    }
+
+    let bytes = match BYTE_UNITS.iter().find(|(name, _)| *name == unit) {
+        Some(&(_, bytes)) => bytes,
+        None if unit.ends_with(&['B', 'b'][..]) => {
+            return invalid(unit_input, ByteSizeError::UnknownUnit)
+        }
+        None => return not_a_byte_size,
+    };
+
+    match scale(whole, fraction, bytes) {
+        Some((_, false)) => invalid(input, ByteSizeError::Fractional),
+        Some((bytes, true)) => match u64::try_from(bytes) {
+            Ok(bytes) => Ok((rest, bytes)),
+            Err(_) => invalid(input, ByteSizeError::Overflow),
+        },
+        None => invalid(input, ByteSizeError::Overflow),
+    }
+}
+
+/// Numbers are decimal, with an optional fraction, or integers in hexadecimal, octal or binary:
+///
+/// ```text
+/// number   = "-"? decimal ("." digits)? | "0x" hex | "0o" octal | "0b" binary
+/// decimal  = "0" | [1-9] ("_"? [0-9])*
+/// digits   = [0-9] ("_"? [0-9])*
+/// ```
+///
+/// Decimal numbers with a fraction are floats and everything else is an integer, which must fit in
+/// an `i64`. There's no scientific notation, `inf` or `NaN`. Anything that starts out looking like
+/// a number but doesn't follow the grammar fails with the reason why.
+fn number(input: &str) -> IResult<'_, Value<'_>> {
+    let starts_with_digit = |s: &str| s.starts_with(|c: char| c.is_ascii_digit());
+    let (negative, unsigned) = match input.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, input),
+    };
+
+    if let Some(rest) = input.strip_prefix('+') {
+        if starts_with_digit(rest) || rest.starts_with('.') || non_finite(rest) {
+            return invalid_number(input, NumberError::LeadingPlus);
+        }
+    }
+    if non_finite(unsigned) {
+        return invalid_number(input, NumberError::NonFinite);
+    }
+    if let Some(rest) = unsigned.strip_prefix('.') {
+        if starts_with_digit(rest) {
+            return invalid_number(unsigned, NumberError::MissingIntegerDigits);
+        }
+    }
+    if !starts_with_digit(unsigned) {
+        return Err(nom::Err::Error(ParseError::new(
+            input,
+            ErrorKind::Nom(nom::error::ErrorKind::Digit),
+        )));
+    }
+
+    if let Some(radix) = radix(unsigned) {
+        if negative {
+            return invalid_number(input, NumberError::SignedPrefix);
+        }
+        return prefixed_integer(input, radix);
+    }
+
+    let after_zero = &unsigned[1..];
+    if unsigned.starts_with('0') && (starts_with_digit(after_zero) || after_zero.starts_with('_')) {
+        return invalid_number(unsigned, NumberError::LeadingZero);
+    }
+
+    let (mut rest, _) = digits(unsigned, 10)?;
+    let mut is_float = false;
+    if let Some(fraction) = rest.strip_prefix('.') {
+        if !starts_with_digit(fraction) {
+            return invalid_number(rest, NumberError::MissingFractionDigits);
+        }
+        rest = digits(fraction, 10)?.0;
+        if rest.starts_with('.') {
+            return invalid_number(rest, NumberError::MultipleDecimalPoints);
+        }
+        is_float = true;
+    }
+    if rest.starts_with(&['e', 'E'][..]) {
+        return invalid_number(rest, NumberError::Exponent);
+    }
+    let (rest, ()) = end_of_number(rest)?;
+
+    let text = input[..input.len() - rest.len()].replace('_', "");
+    let value = if is_float {
+        text.parse()
+            .ok()
+            .filter(|float: &f64| float.is_finite())
+            .map(Value::Float)
+    } else {
+        text.parse().ok().map(Value::Integer)
+    };
+
+    match value {
+        Some(value) => Ok((rest, value)),
+        None => invalid_number(input, NumberError::Overflow),
+    }
+}
+
+/// The radix of an integer with a `0x`, `0o` or `0b` prefix.
+fn radix(input: &str) -> Option<u32> {
+    match input.get(..2)? {
+        "0x" => Some(16),
+        "0o" => Some(8),
+        "0b" => Some(2),
+        _ => None,
+    }
+}
+
+/// An integer with a `0x`, `0o` or `0b` prefix, which `input` starts with.
+fn prefixed_integer(input: &str, radix: u32) -> IResult<'_, Value<'_>> {
+    let after_prefix = &input[2..];
+    if after_prefix.starts_with(|c: char| c.is_alphanumeric())
+        && !after_prefix.starts_with(|c: char| c.is_digit(radix))
+    {
+        return invalid_number(after_prefix, NumberError::InvalidDigit);
+    }
+    if after_prefix.starts_with('_') {
+        return invalid_number(after_prefix, NumberError::MisplacedUnderscore);
+    }
+    let (rest, digits) = match digits(after_prefix, radix) {
+        Err(nom::Err::Error(_)) => return invalid_number(after_prefix, NumberError::MissingDigits),
+        result => result?,
+    };
+    if rest.starts_with(|c: char| c.is_alphanumeric()) {
+        return invalid_number(rest, NumberError::InvalidDigit);
+    }
+    let (rest, ()) = end_of_number(rest)?;
+
+    match i64::from_str_radix(&digits.replace('_', ""), radix) {
+        Ok(integer) => Ok((rest, Value::Integer(integer))),
+        Err(_) => invalid_number(input, NumberError::Overflow),
+    }
+}
+
+/// One or more digits in `radix`, which can be separated by single underscores.
+fn digits(input: &str, radix: u32) -> IResult<'_, &str> {
+    let digit = || satisfy(move |c| c.is_digit(radix));
+    let (rest, digits) = recognize(pair(digit(), many0(preceded(opt(char('_')), digit()))))(input)?;
+
+    if rest.starts_with('_') {
+        return invalid_number(rest, NumberError::MisplacedUnderscore);
+    }
+
+    Ok((rest, digits))
+}
+
+/// Whether the input starts with `inf`, `infinity` or `nan` in any case, which look enough like
+/// numbers to be worth a specific error.
+fn non_finite(input: &str) -> bool {
+    ["infinity", "inf", "nan"].iter().any(|word| {
+        input
+            .get(..word.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(word))
+            && !input[word.len()..].starts_with(|c: char| c.is_alphanumeric() || c == '_')
+    })
+}
+
+/// Checks a number isn't followed by something that could have been part of it, like in `10px`.
+fn end_of_number(rest: &str) -> IResult<'_, ()> {
+    if rest.starts_with(|c: char| c.is_alphanumeric() || c == '.' || c == '_') {
+        return invalid_number(rest, NumberError::TrailingCharacters);
+    }
+
+    Ok((rest, ()))
+}
+
+fn invalid_number<T>(input: &str, reason: NumberError) -> IResult<'_, T> {
+    Err(nom::Err::Failure(ParseError::new(
+        input,
+        ErrorKind::InvalidNumber(reason),
+    )))
+}
+
+enum StringFragment<'a> {
+    Literal(&'a str),
+    Escaped(char),
+}
+
+/// `\u{...}` with one to six hex digits, which must name a valid `char`.
+fn unicode_escape(input: &str) -> IResult<'_, char> {
+    map_opt(
+        delimited(
+            tag("u{"),
+            take_while_m_n(1, 6, |c: char| c.is_ascii_hexdigit()),
+            char('}'),
+        ),
+        |hex| {
+            u32::from_str_radix(hex, 16)
+                .ok()
+                .and_then(std::char::from_u32)
+        },
+    )(input)
+}
+
+fn escape(input: &str) -> IResult<'_, char> {
+    use nom::combinator::value as v;
+
+    let escaped = alt((
+        v('"', char('"')),
+        v('\\', char('\\')),
+        v('\n', char('n')),
+        v('\t', char('t')),
+        unicode_escape,
+    ));
+
+    preceded(char('\\'), cut(escaped))(input).map_err(|e| match e {
+        nom::Err::Failure(_) => nom::Err::Failure(ParseError::new(input, ErrorKind::InvalidEscape)),
+        e => e,
+    })
+}
+
+/// A double quoted string. Strings without escapes are borrowed from the input, otherwise the
+/// unescaped string is built up as an owned `String`.
+fn string(input: &str) -> IResult<'_, Cow<'_, str>> {
+    let fragment = alt((
+        map(is_not("\"\\\n"), StringFragment::Literal),
+        map(escape, StringFragment::Escaped),
+    ));
+    let fragments = fold_many0(fragment, Cow::Borrowed(""), |mut string, fragment| {
+        match fragment {
+            StringFragment::Literal(s) if string.is_empty() => string = Cow::Borrowed(s),
+            StringFragment::Literal(s) => string.to_mut().push_str(s),
+            StringFragment::Escaped(c) => string.to_mut().push(c),
+        }
+        string
+    });
+
+    let result: IResult<Cow<'_, str>> = preceded(char('"'), fragments)(input);
+    let (rest, string) = result?;
+
+    match rest.strip_prefix('"') {
+        Some(rest) => Ok((rest, string)),
+        None => Err(nom::Err::Failure(ParseError::new(
+            input,
+            ErrorKind::UnterminatedString,
+        ))),
+    }
+}
+
+/// A single quoted string. Backslashes have no special meaning, so the contents are always
+/// borrowed straight from the input.
+fn raw_string(input: &str) -> IResult<'_, &str> {
+    let result: IResult<'_, &str> =
+        preceded(char('\''), take_while(|c| c != '\'' && c != '\n'))(input);
+    let (rest, string) = result?;
+
+    match rest.strip_prefix('\'') {
+        Some(rest) => Ok((rest, string)),
+        None => Err(nom::Err::Failure(ParseError::new(
+            input,
+            ErrorKind::UnterminatedRawString,
+        ))),
+    }
+}
+
+#[derive(Clone, Copy)]
+enum BlockStyle {
+    /// `|` keeps line breaks as they are.
+    Literal,
+    /// `>` folds runs of lines into a single line, blank lines become line breaks.
+    Folded,
+}
+
+/// What to do with the line breaks at the end of a block string.
+#[derive(Clone, Copy)]
+enum Chomping {
+    /// The default, keep a single trailing line break.
+    Clip,
+    /// `-` removes all trailing line breaks.
+    Strip,
+    /// `+` keeps all trailing line breaks, including those from trailing blank lines.
+    Keep,
+}
+
+/// The header of a block string, which can be followed by a comment.
+fn block_string_header(input: &str) -> IResult<'_, (BlockStyle, Chomping, Option<&str>)> {
+    use nom::combinator::value as v;
+
+    let style = alt((
+        v(BlockStyle::Literal, char('|')),
+        v(BlockStyle::Folded, char('>')),
+    ));
+    let chomping = map(
+        opt(alt((
+            v(Chomping::Strip, char('-')),
+            v(Chomping::Keep, char('+')),
+        ))),
+        |chomping| chomping.unwrap_or(Chomping::Clip),
+    );
+
+    let (rest, (style, chomping)) = pair(style, chomping)(input)?;
+    let (rest, comment) =
+        terminated(preceded(space0, opt(comment)), peek(alt((tag("\n"), eof))))(rest)?;
+
+    Ok((rest, (style, chomping, comment)))
+}
+
+/// A multi-line string, the header (`|` or `>` with an optional chomping indicator) is followed
+/// by an indented block of lines. The indentation of the first non-blank line is stripped from
+/// every line, and the block ends at the first non-blank line indented less than that. The block
+/// must be indented past `indent`, the indentation of the line the header is on.
+///
+/// The newline ending the last line of the block is left in the input. A comment after the
+/// header is returned with the string.
+fn block_string(input: &str, indent: usize) -> IResult<'_, (Cow<'_, str>, Option<&str>)> {
+    let (rest, (style, chomping, comment)) = block_string_header(input)?;
+    let body = rest.strip_prefix('\n').unwrap_or(rest);
+
+    let mut lines = Vec::new();
+    let mut first_indent = None;
+    let mut start = rest.len() - body.len();
+    let mut end = 0;
+
+    for line in body.split_inclusive('\n') {
+        let content = line.trim_end_matches('\n');
+        let trimmed = content.trim_start_matches(' ');
+
+        if trimmed.is_empty() {
+            lines.push("");
+        } else {
+            let line_indent = content.len() - trimmed.len();
+            let block_indent = *first_indent.get_or_insert(line_indent);
+            if line_indent <= indent || line_indent < block_indent {
+                break;
+            }
+            lines.push(&content[block_indent..]);
+        }
+
+        end = start + content.len();
+        start += line.len();
+    }
+
+    let trailing_blank_lines = lines.iter().rev().take_while(|l| l.is_empty()).count();
+    let lines = &lines[..lines.len() - trailing_blank_lines];
+
+    let mut string = match style {
+        BlockStyle::Literal => lines.join("\n"),
+        // Line breaks between two lines of text are folded into a space, and blank lines between
+        // them are line breaks. Lines indented more than the block are kept as they are, along
+        // with the line breaks around them.
+        BlockStyle::Folded => {
+            let more_indented = |line: &str| line.starts_with(&[' ', '\t'][..]);
+            let mut string = String::new();
+            let mut previous: Option<&str> = None;
+            let mut blank_lines = 0;
+            for line in lines {
+                if line.is_empty() {
+                    blank_lines += 1;
+                    continue;
+                }
+
+                let line_breaks = match previous {
+                    Some(previous) if more_indented(previous) || more_indented(line) => {
+                        blank_lines + 1
+                    }
+                    _ => blank_lines,
+                };
+                if previous.is_some() && line_breaks == 0 {
+                    string.push(' ');
+                }
+                string.push_str(&"\n".repeat(line_breaks));
+                string.push_str(line);
+
+                previous = Some(line);
+                blank_lines = 0;
+            }
+            string
+        }
+    };
+
+    let trailing_newlines = match (chomping, lines.is_empty()) {
+        (Chomping::Strip, _) => 0,
+        (Chomping::Clip, true) => 0,
+        (Chomping::Clip, false) => 1,
+        (Chomping::Keep, true) => trailing_blank_lines,
+        (Chomping::Keep, false) => trailing_blank_lines + 1,
+    };
+    string.push_str(&"\n".repeat(trailing_newlines));
+
+    Ok((&rest[end..], (Cow::Owned(string), comment)))
+}
+
+/// The number of spaces at the start of a line. Tabs can't be used for indentation.
+fn indentation(input: &str) -> IResult<'_, usize> {
+    let (rest, spaces) = take_while(|c| c == ' ')(input)?;
+
+    if rest.starts_with('\t') {
+        return Err(nom::Err::Failure(ParseError::new(
+            rest,
+            ErrorKind::BadIndent,
+        )));
+    }
+
+    Ok((rest, spaces.len()))
+}
+
+/// A `#` comment running to the end of the line. The text of the comment excludes the `#`.
+fn comment(input: &str) -> IResult<'_, &str> {
+    preceded(char('#'), not_line_ending)(input)
+}
+
+fn newline_or_eof(input: &str) -> IResult<'_, &str> {
+    alt((tag("\n"), eof))(input)
+}
+
+/// Optional trailing whitespace and comment, followed by a newline or the end of the input. A
+/// comment is attached to the value currently being parsed.
+fn end_of_line<'a>(input: &'a str, ctx: &mut Context<'a>) -> IResult<'a, ()> {
+    let (rest, comment) = preceded(space0, opt(comment))(input)?;
+    let (rest, _) = newline_or_eof(rest)?;
+
+    if let Some(text) = comment {
+        ctx.comment(text, CommentKind::Trailing);
+    }
+
+    Ok((rest, ()))
+}
+
+/// Any number of lines containing only whitespace or a comment, including trailing whitespace at
+/// the end of the input. Comments are held on to until the next value is parsed.
+fn blank_lines<'a>(input: &'a str, ctx: &mut Context<'a>) -> IResult<'a, ()> {
+    let mut input = input;
+
+    loop {
+        let (rest, comment) = preceded(space0, opt(comment))(input)?;
+        let rest = match newline_or_eof(rest) {
+            Ok((rest, _)) => rest,
+            Err(_) => return Ok((input, ())),
+        };
+
+        if let Some(text) = comment {
+            ctx.pending_comments.push(text);
+        }
+
+        if rest.is_empty() {
+            return Ok((rest, ()));
+        }
+        input = rest;
+    }
+}
+
+/// Called after each entry of a block indented by `indent`. Skips blank lines, then checks whether
+/// the next line is another entry in the same block (`true`), or the block has ended because the
+/// input has ended or the next line is dedented (`false`). An indented line is an error as it
+/// isn't preceded by a key or array item to nest under.
+///
+/// The returned input is at the start of the next line, before its indentation.
+fn continues_block<'a>(input: &'a str, indent: usize, ctx: &mut Context<'a>) -> IResult<'a, bool> {
+    let (rest, ()) = blank_lines(input, ctx)?;
+
+    if rest.is_empty() {
+        return Ok((rest, false));
+    }
+
+    let (_, next_indent) = indentation(rest)?;
+    match next_indent.cmp(&indent) {
+        Ordering::Equal => Ok((rest, true)),
+        Ordering::Less => Ok((rest, false)),
+        Ordering::Greater => Err(nom::Err::Failure(ParseError::new(
+            rest,
+            ErrorKind::BadIndent,
+        ))),
+    }
+}
+
+/// The `-` starting an array item, when followed by a space or the end of the line.
+fn array_item_marker(input: &str) -> IResult<'_, char> {
+    terminated(char('-'), peek(alt((tag(" "), tag("\n"), eof))))(input)
+}
+
+/// What follows the `-` of an array item. Either a value on the same line, nothing on this line
+/// and an indented collection starting on the next line, or a compact collection starting on the
+/// same line whose later entries line up with the first, e.g.
+///
+/// ```text
+/// - name: a
+///   port: 1
+/// - - 1
+///   - 2
+/// ```
+fn array_item<'a>(input: &'a str, indent: usize, ctx: &mut Context<'a>) -> IResult<'a, Value<'a>> {
+    if let Ok((rest, ())) = end_of_line(input, ctx) {
+        ctx.span(input);
+        return nested_collection(rest, indent, ctx);
+    }
+
+    let (rest, spaces) = take_while1(|c| c == ' ')(input)?;
+    let compact_indent = indent + 1 + spaces.len();
+
+    if array_item_marker(rest).is_ok()
+        || peek(terminated(
+            |i| dotted_key(i, ctx),
+            pair(char(':'), alt((space1, tag("\n"), eof))),
+        ))(rest)
+        .is_ok()
+    {
+        return collection(rest, compact_indent, ctx);
+    }
+
+    let (rest, value) = value(rest, indent, ctx)?;
+    let (rest, ()) = end_of_line(rest, ctx)?;
+    Ok((rest, value))
+}
+
+fn array<'a>(input: &'a str, indent: usize, ctx: &mut Context<'a>) -> IResult<'a, Vec<Value<'a>>> {
+    let mut array = Vec::new();
+    let mut input = input;
+
+    loop {
+        let (rest, _) = array_item_marker(input)?;
+
+        ctx.enter(PathSegment::Index(array.len()));
+        ctx.start_node(1, false, input, rest);
+        let (rest, item) = array_item(rest, indent, ctx)?;
+        ctx.end_node();
+        ctx.exit();
+
+        array.push(item);
+
+        let (rest, continues) = continues_block(rest, indent, ctx)?;
+        if !continues {
+            return Ok((rest, array));
+        }
+        input = &rest[indent..];
+    }
+}
+
+/// A key is either quoted, following the same rules as string values, or bare. Bare keys are
+/// words of letters, digits, `_` and `-`, which can be separated by spaces:
+///
+/// ```text
+/// key      = string | raw_string | bare_key
+/// bare_key = word (" "+ word)*
+/// word     = (alphanumeric | "_" | "-")+
+/// ```
+///
+/// Spaces between the key and the `:` aren't part of the key.
+fn key(input: &str) -> IResult<'_, Cow<'_, str>> {
+    let word = || take_while1(|c: char| c.is_alphanumeric() || c == '_' || c == '-');
+    let bare_key = recognize(pair(word(), many0(pair(space1, word()))));
+
+    terminated(
+        alt((
+            string,
+            map(raw_string, Cow::Borrowed),
+            map(bare_key, Cow::Borrowed),
+        )),
+        space0,
+    )(input)
+}
+
+/// A key, or several separated by `.` as shorthand for nested objects, e.g. `a.b: 1` is the same
+/// as `a:` followed by `b: 1` indented on the next line.
+struct DottedKey<'a> {
+    /// The keys of the objects the value is nested in, `a` and `b` in `a.b.c`.
+    parents: Vec<Cow<'a, str>>,
+    key: Cow<'a, str>,
+}
+
+impl<'a> DottedKey<'a> {
+    fn len(&self) -> usize {
+        self.parents.len() + 1
+    }
+
+    fn segments(&self) -> impl Iterator<Item = &Cow<'a, str>> {
+        self.parents.iter().chain(std::iter::once(&self.key))
+    }
+
+    /// The first `len` keys joined with `.`, for error messages.
+    fn join(&self, len: usize) -> Cow<'a, str> {
+        if len == 1 && self.parents.is_empty() {
+            return self.key.clone();
+        }
+
+        Cow::Owned(
+            self.segments()
+                .take(len)
+                .map(|key| key.as_ref())
+                .collect::<Vec<_>>()
+                .join("."),
+        )
+    }
+}
+
+/// Keys separated by `.`, which can have spaces after them.
+fn dotted_key<'a>(input: &'a str, ctx: &Context<'a>) -> IResult<'a, DottedKey<'a>> {
+    let (rest, (first, others)) = pair(key, many0(preceded(pair(char('.'), space0), key)))(input)?;
+
+    let mut parents = Vec::new();
+    let mut key = first;
+    for other in others {
+        parents.push(std::mem::replace(&mut key, other));
+    }
+
+    // Each key is an object nested in the last, and dotted keys don't go through `collection`.
+    if ctx.path.len() + parents.len() >= MAX_DEPTH {
+        return Err(nom::Err::Failure(ParseError::new(
+            input,
+            ErrorKind::TooDeep,
+        )));
+    }
+
+    Ok((rest, DottedKey { parents, key }))
+}
+
+/// What follows the `:` of a key. Either a value on the same line, or nothing on this line and an
+/// indented collection starting on the next line.
+fn entry_value<'a>(input: &'a str, indent: usize, ctx: &mut Context<'a>) -> IResult<'a, Value<'a>> {
+    if let Ok((rest, ())) = end_of_line(input, ctx) {
+        // Where the value would be if it's null, replaced by the collection if there is one.
+        ctx.span(input);
+        return nested_collection(rest, indent, ctx);
+    }
+
+    let (rest, _) = space1(input)?;
+    let (rest, value) = value(rest, indent, ctx)?;
+    let (rest, ()) = end_of_line(rest, ctx)?;
+    Ok((rest, value))
+}
+
+/// Adds the entries of `new` to `object`, merging the objects under keys they both have.
+fn merge<'a>(object: &mut Map<'a>, new: Map<'a>) {
+    for (key, value) in new {
+        match (object.get_mut(&key), value) {
+            (Some(Value::Object(existing)), Value::Object(new)) => merge(existing, new),
+            (_, value) => {
+                object.insert(key, value);
+            }
+        }
+    }
+}
+
+fn duplicate_key<'a>(
+    input: &'a str,
+    key: Cow<'a, str>,
+    first: &'a str,
+) -> nom::Err<ParseError<'a>> {
+    nom::Err::Failure(ParseError::new(
+        input,
+        ErrorKind::DuplicateKey { key, first },
+    ))
+}
+
+fn conflicting_key<'a>(
+    input: &'a str,
+    key: Cow<'a, str>,
+    first: &'a str,
+) -> nom::Err<ParseError<'a>> {
+    nom::Err::Failure(ParseError::new(
+        input,
+        ErrorKind::ConflictingKey { key, first },
+    ))
+}
+
+fn object<'a>(input: &'a str, indent: usize, ctx: &mut Context<'a>) -> IResult<'a, Map<'a>> {
+    let mut object = Map::new();
+    let mut input = input;
+
+    loop {
+        let (rest, key) = dotted_key(input, ctx)?;
+        let (rest, _) = char(':')(rest).map_err(|_: nom::Err<ParseError>| {
+            nom::Err::Failure(ParseError::new(rest, ErrorKind::MissingColon))
+        })?;
+        ctx.check_duplicate(input, &key)?;
+
+        ctx.enter_key(&key);
+        ctx.start_node(key.len(), false, input, rest);
+        let (rest, value) = entry_value(rest, indent, ctx)?;
+        ctx.end_node();
+        ctx.exit_key(&key);
+
+        ctx.insert(&mut object, input, key, value)?;
+
+        let (rest, continues) = continues_block(rest, indent, ctx)?;
+        if !continues {
+            return Ok((rest, object));
+        }
+        input = &rest[indent..];
+    }
+}
+
+/// Skips spaces inside a flow collection, which has to be closed on the line it was opened on.
+/// `open` is the input at the opening bracket.
+fn flow_space<'a>(open: &'a str, input: &'a str) -> IResult<'a, ()> {
+    let (rest, _) = space0(input)?;
+
+    if rest.is_empty() || rest.starts_with(&['\n', '#'][..]) {
+        return Err(nom::Err::Failure(ParseError::new(
+            open,
+            ErrorKind::UnclosedFlow,
+        )));
+    }
+
+    Ok((rest, ()))
+}
+
+/// What follows an item in a flow collection, either a `,` (`true`) or the `close` bracket ending
+/// the collection (`false`). `open` is the input at the opening bracket.
+fn flow_separator<'a>(open: &'a str, input: &'a str, close: char) -> IResult<'a, bool> {
+    let (rest, ()) = flow_space(open, input)?;
+
+    if let Some(rest) = rest.strip_prefix(',') {
+        Ok((rest, true))
+    } else if let Some(rest) = rest.strip_prefix(close) {
+        Ok((rest, false))
+    } else {
+        Err(nom::Err::Failure(ParseError::new(
+            rest,
+            ErrorKind::Nom(nom::error::ErrorKind::Char),
+        )))
+    }
+}
+
+/// An array on a single line, like `[1, [2, 3], { a: 4 }]`. The last item can be followed by a
+/// comma.
+fn flow_array<'a>(input: &'a str, indent: usize, ctx: &mut Context<'a>) -> IResult<'a, Value<'a>> {
+    check_depth(input, ctx)?;
+    let mut rest = &input[1..];
+    let mut array = Vec::new();
+
+    loop {
+        let (item, ()) = flow_space(input, rest)?;
+        if let Some(rest) = item.strip_prefix(']') {
+            return Ok((rest, Value::Array(array)));
+        }
+
+        ctx.enter(PathSegment::Index(array.len()));
+        ctx.start_node(1, true, item, item);
+        let (after, value) = value(item, indent, ctx)?;
+        ctx.end_node();
+        ctx.exit();
+
+        array.push(value);
+
+        let (after, more) = flow_separator(input, after, ']')?;
+        if !more {
+            return Ok((after, Value::Array(array)));
+        }
+        rest = after;
+    }
+}
+
+/// An object on a single line, like `{ host: "x", port: 1 }`. The last entry can be followed by a
+/// comma.
+fn flow_object<'a>(input: &'a str, indent: usize, ctx: &mut Context<'a>) -> IResult<'a, Value<'a>> {
+    check_depth(input, ctx)?;
+    let mut rest = &input[1..];
+    let mut object = Map::new();
+
+    loop {
+        let (entry, ()) = flow_space(input, rest)?;
+        if let Some(rest) = entry.strip_prefix('}') {
+            return Ok((rest, Value::Object(object)));
+        }
+
+        let (after, key) = cut(|i| dotted_key(i, ctx))(entry)?;
+        let (slot, _) = char(':')(after).map_err(|_: nom::Err<ParseError>| {
+            nom::Err::Failure(ParseError::new(after, ErrorKind::MissingColon))
+        })?;
+        let (after, _) = cut(space1)(slot)?;
+        ctx.check_duplicate(entry, &key)?;
+
+        ctx.enter_key(&key);
+        ctx.start_node(key.len(), true, entry, slot);
+        let (after, value) = value(after, indent, ctx)?;
+        ctx.end_node();
+        ctx.exit_key(&key);
+
+        ctx.insert(&mut object, entry, key, value)?;
+
+        let (after, more) = flow_separator(input, after, '}')?;
+        if !more {
+            return Ok((after, Value::Object(object)));
+        }
+        rest = after;
+    }
+}
+
+/// Checks a collection starting at `input` wouldn't be nested too deeply.
+fn check_depth<'a>(input: &'a str, ctx: &Context<'a>) -> IResult<'a, ()> {
+    if ctx.path.len() >= MAX_DEPTH {
+        return Err(nom::Err::Failure(ParseError::new(
+            input,
+            ErrorKind::TooDeep,
+        )));
+    }
+
+    Ok((input, ()))
+}
+
+/// A value that starts on the current line. `indent` is the indentation of the line, which block
+/// strings need to be indented past.
+fn value<'a>(input: &'a str, indent: usize, ctx: &mut Context<'a>) -> IResult<'a, Value<'a>> {
+    ctx.span(input);
+
+    let (rest, value) = if input.starts_with('[') {
+        flow_array(input, indent, ctx)?
+    } else if input.starts_with('{') {
+        flow_object(input, indent, ctx)?
+    } else if input.starts_with(&['|', '>'][..]) {
+        let (rest, (string, comment)) = block_string(input, indent).map_err(|e| match e {
+            nom::Err::Error(_) => {
+                nom::Err::Failure(ParseError::new(input, ErrorKind::ExpectedValue))
+            }
+            e => e,
+        })?;
+        if let Some(text) = comment {
+            ctx.comment(text, CommentKind::Trailing);
+        }
+        (rest, Value::String(string))
+    } else {
+        scalar(input)?
+    };
+
+    ctx.span_end(rest);
+    Ok((rest, value))
+}
+
+/// A value other than a flow collection or block string.
+fn scalar(input: &str) -> IResult<'_, Value<'_>> {
+    alt((
+        map(string, Value::String),
+        map(raw_string, |s| Value::String(Cow::Borrowed(s))),
+        date_time,
+        map(byte_size, Value::Bytes),
+        map(duration, Value::Duration),
+        number,
+        map(boolean, Value::Bool),
+        map(null, |_| Value::Null),
+    ))(input)
+    .map_err(|e| match e {
+        nom::Err::Error(_) => nom::Err::Failure(ParseError::new(input, ErrorKind::ExpectedValue)),
+        e => e,
+    })
+}
+
+/// An array or object where each entry is on its own line, indented by `indent`. The input is at
+/// the start of the first entry, after its indentation.
+fn collection<'a>(input: &'a str, indent: usize, ctx: &mut Context<'a>) -> IResult<'a, Value<'a>> {
+    check_depth(input, ctx)?;
+    ctx.span(input);
+
+    if array_item_marker(input).is_ok() {
+        let (rest, array) = array(input, indent, ctx)?;
+        Ok((rest, Value::Array(array)))
+    } else {
+        let (rest, object) = object(input, indent, ctx)?;
+        Ok((rest, Value::Object(object)))
+    }
+}
+
+/// A collection starting on the next non-blank line, which must be indented past `indent`, the
+/// indentation of the line it's nested under. If the next line isn't indented past it there's
+/// nothing nested, which is null.
+fn nested_collection<'a>(
+    input: &'a str,
+    indent: usize,
+    ctx: &mut Context<'a>,
+) -> IResult<'a, Value<'a>> {
+    let (rest, ()) = blank_lines(input, ctx)?;
+    let (content, nested_indent) = indentation(rest)?;
+
+    // Nothing nested, so the key or array item is null.
+    if rest.is_empty() || nested_indent <= indent {
+        return Ok((rest, Value::Null));
+    }
+
+    collection(content, nested_indent, ctx)
+}
+
+pub fn parse(input: &str) -> Result<Value<'_>, Error> {
+    ParseOptions::default().parse(input)
+}
+
+/// Parses the input like [`parse`], also returning the comments found along the way.
+pub fn parse_with_comments(input: &str) -> Result<(Value<'_>, Comments<'_>), Error> {
+    ParseOptions::default().parse_with_comments(input)
+}
+
+/// Parses the input like [`parse`], also returning where each value starts.
+#[cfg(feature = "serde")]
+fn parse_with_spans(input: &str) -> Result<(Value<'_>, Spans<'_>), Error> {
+    let ctx = Context {
+        spans: Some(Spans::new()),
+        ..Context::default()
+    };
+
+    document(input, ctx)
+        .map(|(_, (value, ctx))| (value, ctx.spans.unwrap_or_default()))
+        .map_err(|e| Error::new(input, e))
+}
+
+/// Parses the input like [`parse`], also returning how each entry and array item is written.
+fn parse_with_syntax(input: &str) -> Result<(Value<'_>, Vec<SyntaxNode<'_>>), Error> {
+    let ctx = Context {
+        syntax: Some(Syntax::default()),
+        ..Context::default()
+    };
+
+    document(input, ctx)
+        .map(|(_, (value, ctx))| (value, ctx.syntax.unwrap_or_default().nodes))
+        .map_err(|e| Error::new(input, e))
+}
+
+fn document<'a>(input: &'a str, mut ctx: Context<'a>) -> IResult<'a, (Value<'a>, Context<'a>)> {
+    let (rest, ()) = blank_lines(input, &mut ctx)?;
+
+    // Nothing but blank lines and comments is an empty document.
+    let (rest, value) = if rest.is_empty() {
+        (rest, Value::Object(Map::new()))
+    } else {
+        let (content, indent) = indentation(rest)?;
+        collection(content, indent, &mut ctx)?
+    };
+
+    // Collections only end early when the next line is dedented past the top level.
+    if !rest.is_empty() {
+        return Err(nom::Err::Failure(ParseError::new(
+            rest,
+            ErrorKind::BadIndent,
+        )));
+    }
+
+    // Comments after the last value belong to the document as a whole.
+    for text in std::mem::take(&mut ctx.pending_comments) {
+        ctx.comments
+            .push(Vec::new(), Comment::new(text, CommentKind::Trailing));
+    }
+
+    Ok((rest, (value, ctx)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    fn position(offset: usize, line: usize, column: usize) -> Position {
+        Position {
+            offset,
+            line,
+            column,
+        }
+    }
+
+    fn unwrap_object<'a>(input: &'a str) -> Map<'a> {
+        match parse(input).unwrap() {
+            Value::Object(o) => o,
+            _ => panic!("not an object"),
+        }
+    }
+
+    fn unwrap_array<'a>(input: &'a str) -> Vec<Value<'a>> {
+        match parse(input).unwrap() {
+            Value::Array(a) => a,
+            _ => panic!("not an object"),
+        }
+    }
+
+    #[test]
+    fn fixtures() {
+        for fixture in fixtures::DOCUMENTS {
+            match parse(fixture) {
+                Ok(Value::Object(object)) => {
+                    assert_eq!(
+                        object.is_empty(),
+                        *fixture == fixtures::EMPTY,
+                        "{:?}",
+                        fixture
+                    )
+                }
+                Ok(Value::Array(array)) => assert!(!array.is_empty(), "{:?}", fixture),
+                result => panic!("{:?} from {:?}", result, fixture),
+            }
+        }
+    }
+
+    #[test]
+    fn nested() {
+        let input = fixtures::NESTED;
+
+        let mut obj = Map::new();
+        obj.insert("nested".into(), Value::Integer(456));
+
+        let mut expected = Map::new();
+        expected.insert("key_1".into(), Value::Integer(123));
+        expected.insert("obj".into(), Value::Object(obj));
+
+        assert_eq!(unwrap_object(input), expected);
+    }
+
+    #[test]
+    fn nested_double() {
+        let input = fixtures::NESTED_DOUBLE;
+
+        let mut nested = Map::new();
+        nested.insert("nested_again".into(), Value::Integer(789));
+
+        let mut obj = Map::new();
+        obj.insert("nested".into(), Value::Object(nested));
+
+        let mut expected = Map::new();
+        expected.insert("key_1".into(), Value::Integer(123));
+        expected.insert("obj".into(), Value::Object(obj));
+
+        assert_eq!(unwrap_object(input), expected);
+    }
+
+    #[test]
+    fn nested_under_value() {
+        let input = indoc! {r#"
+            key_1: 123
+            obj:
+                nested: 456
+                    nested_again: 789
+        "#};
+
+        assert_eq!(parse(input), Err(Error::BadIndent(position(32, 4, 1))));
+    }
+
+    #[test]
+    fn nested_deep_with_dedents() {
+        let input = fixtures::NESTED_DEEP_WITH_DEDENTS;
+
+        let mut c = Map::new();
+        c.insert("d".into(), Value::Integer(1));
+
+        let mut b = Map::new();
+        b.insert("c".into(), Value::Object(c));
+
+        let mut a = Map::new();
+        a.insert("b".into(), Value::Object(b));
+        a.insert("e".into(), Value::Integer(2));
+
+        let mut expected = Map::new();
+        expected.insert("a".into(), Value::Object(a));
+        expected.insert(
+            "f".into(),
+            Value::Array(vec![Value::Integer(3), Value::Integer(4)]),
+        );
+
+        assert_eq!(unwrap_object(input), expected);
+    }
+
+    #[test]
+    fn nested_with_different_indent_widths() {
+        let input = fixtures::NESTED_WITH_DIFFERENT_INDENT_WIDTHS;
+
+        let mut b = Map::new();
+        b.insert("c".into(), Value::Integer(1));
+
+        let mut a = Map::new();
+        a.insert("b".into(), Value::Object(b));
+        a.insert("d".into(), Value::Integer(2));
+
+        let mut expected = Map::new();
+        expected.insert("a".into(), Value::Object(a));
+
+        assert_eq!(unwrap_object(input), expected);
+    }
+
+    #[test]
+    fn blank_lines_between_entries() {
+        let input = "\n\na: 1\n\n  \nb:\n\n    c: 2\n\n";
+
+        let mut b = Map::new();
+        b.insert("c".into(), Value::Integer(2));
+
+        let mut expected = Map::new();
+        expected.insert("a".into(), Value::Integer(1));
+        expected.insert("b".into(), Value::Object(b));
+
+        assert_eq!(unwrap_object(input), expected);
+    }
+
+    #[test]
+    fn inconsistent_dedent() {
+        let input = indoc! {r#"
+            a:
+                b: 1
+              c: 2
+        "#};
+
+        assert_eq!(parse(input), Err(Error::BadIndent(position(12, 3, 1))));
+    }
+
+    #[test]
+    fn dedent_past_top_level() {
+        let input = "  a: 1\nb: 2\n";
+
+        assert_eq!(parse(input), Err(Error::BadIndent(position(7, 2, 1))));
+    }
+
+    #[test]
+    fn tab_indentation() {
+        let input = "a:\n\tb: 1\n";
+
+        assert_eq!(parse(input), Err(Error::BadIndent(position(3, 2, 1))));
+    }
+
+    #[test]
+    fn block_string_nested() {
+        let input = fixtures::BLOCK_STRING_NESTED;
+
+        let mut obj = Map::new();
+        obj.insert("text".into(), Value::String("line 1\n  line 2\n".into()));
+        obj.insert("next".into(), Value::Integer(1));
+
+        let mut expected = Map::new();
+        expected.insert("obj".into(), Value::Object(obj));
+
+        assert_eq!(unwrap_object(input), expected);
+    }
+
+    #[test]
+    fn nested_then_unnested() {
+        let input = fixtures::NESTED_THEN_UNNESTED;
+
+        let mut obj = Map::new();
+        obj.insert("nested".into(), Value::Integer(456));
+
+        let mut expected = Map::new();
+        expected.insert("key_1".into(), Value::Integer(123));
+        expected.insert("obj".into(), Value::Object(obj));
+        expected.insert("top_level".into(), Value::Integer(789));
+
+        assert_eq!(unwrap_object(input), expected);
+    }
+
+    #[test]
+    fn big_array() {
+        let input = fixtures::BIG_ARRAY;
+
+        let expected = vec![
+            Value::Integer(123),
+            Value::String("a string!".into()),
+            Value::Float(1.5),
+            Value::Bool(true),
+            Value::Bool(false),
+        ];
+
+        assert_eq!(unwrap_array(input), expected);
+    }
+
+    #[test]
+    fn array_of_objects() {
+        let input = fixtures::ARRAY_OF_OBJECTS;
+
+        let mut alpha = Map::new();
+        alpha.insert("name".into(), Value::String("alpha".into()));
+        alpha.insert("port".into(), Value::Integer(8080));
+
+        let mut beta = Map::new();
+        beta.insert("name".into(), Value::String("beta".into()));
+        beta.insert("port".into(), Value::Integer(8081));
+        beta.insert(
+            "tags".into(),
+            Value::Array(vec![Value::String("a".into()), Value::String("b".into())]),
+        );
+
+        let mut expected = Map::new();
+        expected.insert(
+            "servers".into(),
+            Value::Array(vec![Value::Object(alpha), Value::Object(beta)]),
+        );
+
+        assert_eq!(unwrap_object(input), expected);
+    }
+
+    #[test]
+    fn array_item_nested_on_next_line() {
+        let input = fixtures::ARRAY_ITEM_NESTED_ON_NEXT_LINE;
+
+        let mut object = Map::new();
+        object.insert("key".into(), Value::Integer(3));
+
+        let expected = vec![
+            Value::Array(vec![Value::Integer(1), Value::Integer(2)]),
+            Value::Object(object),
+        ];
+
+        assert_eq!(unwrap_array(input), expected);
+    }
+
+    #[test]
+    fn array_compact_nested_arrays() {
+        let input = fixtures::ARRAY_COMPACT_NESTED_ARRAYS;
+
+        let expected = vec![
+            Value::Array(vec![
+                Value::Integer(1),
+                Value::Array(vec![Value::Integer(2), Value::Integer(3)]),
+            ]),
+            Value::Integer(4),
+        ];
+
+        assert_eq!(unwrap_array(input), expected);
+    }
+
+    #[test]
+    fn array_compact_object_misaligned() {
+        let input = indoc! {r#"
+            - name: "alpha"
+               port: 8080
+        "#};
+
+        assert_eq!(parse(input), Err(Error::BadIndent(position(16, 2, 1))));
+    }
+
+    #[test]
+    fn array_item_without_value() {
+        let input = "- 1\n-\n- 2\n";
+
+        assert_eq!(
+            unwrap_array(input),
+            [Value::Integer(1), Value::Null, Value::Integer(2)]
+        );
+    }
+
+    #[test]
+    fn big_object() {
+        let input = fixtures::BIG_OBJECT;
+
+        let mut expected = Map::new();
+        expected.insert("key_1".into(), Value::Integer(123));
+        expected.insert("keytwo".into(), Value::String("a string!".into()));
+        expected.insert("afloat".into(), Value::Float(1.5));
+        expected.insert("truthy".into(), Value::Bool(true));
+        expected.insert("falsey".into(), Value::Bool(false));
+
+        assert_eq!(unwrap_object(input), expected);
+    }
+
+    #[test]
+    fn it_works() {
+        let input = fixtures::IT_WORKS;
+
+        let mut obj = Map::new();
+        obj.insert("nested".into(), Value::Integer(456));
+
+        let mut expected = Map::new();
+        expected.insert("key_1".into(), Value::Integer(123));
+        expected.insert("key_2".into(), Value::String("a string!".into()));
+        expected.insert("a_float".into(), Value::Float(1.5));
+        expected.insert("truthy".into(), Value::Bool(true));
+        expected.insert("falsey".into(), Value::Bool(false));
+        expected.insert("obj".into(), Value::Object(obj));
+
+        assert_eq!(unwrap_object(input), expected);
+    }
+
+    #[test]
+    fn simple_array() {
+        let input = fixtures::SIMPLE_ARRAY;
+
+        let expected = vec![Value::Integer(1), Value::Integer(2)];
+
+        assert_eq!(unwrap_array(input), expected);
+    }
+
+    #[test]
+    fn simple_object() {
+        let input = fixtures::SIMPLE_OBJECT;
+
+        let mut expected = Map::new();
+        expected.insert("keyone".into(), Value::Integer(123));
+        expected.insert("keytwo".into(), Value::Integer(456));
+
+        assert_eq!(unwrap_object(input), expected);
+    }
+
+    #[test]
+    fn array_then_object() {
+        let input = indoc! {r#"
+            - 1
+            foo: 2
+        "#};
+
+        assert!(parse(input).is_err());
+    }
+
+    #[test]
+    fn empty_object() {
+        let input = "key_1:";
+
+        let mut expected = Map::new();
+        expected.insert("key_1".into(), Value::Null);
+
+        assert_eq!(unwrap_object(input), expected);
+    }
+
+    #[test]
+    fn empty() {
+        for input in &["", "\n", "  \n\n   "] {
+            assert_eq!(parse(input), Ok(Value::Object(Map::new())), "{:?}", input);
+        }
+    }
 
-use std::collections::HashMap;
+    #[test]
+    fn only_comments() {
+        let input = indoc! {r#"
+            # all settings commented out
 
-use nom::{
-    branch::alt,
-    bytes::complete::tag,
-    character::complete::{alphanumeric1, char, newline},
-    combinator::{eof, map, opt, recognize},
-    multi::{many1, separated_list1},
-    number::complete::double,
-    sequence::{preceded, separated_pair, terminated},
-    IResult,
-};
+            # key: 1
+        "#};
 
-#[derive(Debug, PartialEq)]
-pub enum Value<'a> {
-    String(&'a str),
-    Number(f64),
-    Bool(bool),
-    Object(HashMap<&'a str, Value<'a>>),
-    Array(Vec<Value<'a>>),
-}
+        let (value, comments) = parse_with_comments(input).unwrap();
+        assert_eq!(value, Value::Object(Map::new()));
+        assert_eq!(
+            comments.get(&[]),
+            &[
+                Comment::new(" all settings commented out", CommentKind::Trailing),
+                Comment::new(" key: 1", CommentKind::Trailing)
+            ]
+        );
+    }
 
-fn boolean(input: &str) -> IResult<&str, bool> {
-    use nom::combinator::value as v;
+    #[test]
+    fn missing_colon() {
+        let input = "key_1";
 
-    alt((v(true, tag("true")), v(false, tag("false"))))(input)
-}
+        assert_eq!(parse(input), Err(Error::MissingColon(position(5, 1, 6))));
+    }
 
-// TODO: `double` supports scientific notation which seems overly complicated for a config
-// language. Let's write our own f64 parser.
-fn number(input: &str) -> IResult<&str, f64> {
-    double(input)
-}
+    #[test]
+    fn key_with_spaces() {
+        let input = "foo bar: 123";
 
-// TODO: single quote strings
-// TODO: escaping
-fn string(input: &str) -> IResult<&str, &str> {
-    preceded(
-        char('\"'),
-        terminated(
-            // TODO
-            tag("a string!"),
-            char('\"'),
-        ),
-    )(input)
-}
+        let mut expected = Map::new();
+        expected.insert("foo bar".into(), Value::Integer(123));
 
-fn array<'a>(input: &'a str) -> IResult<&str, Vec<Value<'a>>> {
-    many1(preceded(tag("- "), terminated(value, newline)))(input)
-}
+        assert_eq!(unwrap_object(input), expected);
+    }
 
-fn key(input: &str) -> IResult<&str, &str> {
-    let underscore = tag("_");
-    recognize(many1(alt((alphanumeric1, underscore, tag(" ")))))(input)
-}
+    #[test]
+    fn key_with_spaces_and_missing_colon() {
+        let input = "foo bar";
 
-fn object<'a>(input: &'a str) -> IResult<&str, HashMap<&str, Value<'a>>> {
-    // TODO: Handle indentation properly
-    let key_value = separated_pair(key, alt((tag(": "), tag(":\n    "))), value);
-    let key_values = separated_list1(newline, key_value);
-    map(key_values, |tuple_vec| {
-        tuple_vec
-            .into_iter()
-            .map(|(k, v)| (k, v))
-            .collect::<HashMap<_, _>>()
-    })(input)
-}
+        assert_eq!(parse(input), Err(Error::MissingColon(position(7, 1, 8))));
+    }
 
-fn value<'a>(input: &'a str) -> IResult<&str, Value<'a>> {
-    alt((
-        map(string, Value::String),
-        map(number, Value::Number),
-        map(boolean, Value::Bool),
-        collection,
-    ))(input)
-}
+    #[test]
+    fn bare_keys() {
+        let input = fixtures::BARE_KEYS;
+
+        let object = unwrap_object(input);
+        assert_eq!(
+            object.keys().collect::<Vec<_>>(),
+            [
+                "kebab-case",
+                "snake_case",
+                "spaced  out key",
+                "-leading-dash",
+                "123",
+                "flow"
+            ]
+        );
+        match &object["flow"] {
+            Value::Object(flow) => assert_eq!(flow.keys().collect::<Vec<_>>(), ["inner key"]),
+            v => panic!("expected an object, got {:?}", v),
+        }
+    }
 
-fn collection<'a>(input: &'a str) -> IResult<&str, Value<'a>> {
-    alt((map(array, Value::Array), map(object, Value::Object)))(input)
-}
+    #[test]
+    fn unicode_keys() {
+        let input = fixtures::UNICODE_KEYS;
 
-pub fn parse<'a>(input: &'a str) -> IResult<&str, Value<'a>> {
-    let (_, lines) = nom_indent::indent(input, "<assertion>").expect("input failed to parse");
+        assert_eq!(
+            unwrap_object(input).keys().collect::<Vec<_>>(),
+            ["ключ", "名前", "café au lait", "Δt"]
+        );
+    }
 
-    dbg!(lines);
+    #[test]
+    fn quoted_keys() {
+        let input = fixtures::QUOTED_KEYS;
+
+        let object = unwrap_object(input);
+        assert_eq!(
+            object.keys().collect::<Vec<_>>(),
+            [
+                "x-request-id",
+                "a.b/c: d\n",
+                "C:\\path",
+                "",
+                "\u{1F600}",
+                "nested"
+            ]
+        );
+        assert!(matches!(
+            object.get_index(0),
+            Some((Cow::Borrowed("x-request-id"), _))
+        ));
+        assert!(matches!(object.get_index(1), Some((Cow::Owned(_), _))));
+    }
 
-    terminated(collection, preceded(opt(newline), eof))(&input)
-}
+    #[test]
+    fn invalid_keys() {
+        assert_eq!(parse("a/b: 1"), Err(Error::MissingColon(position(1, 1, 2))));
+        assert_eq!(
+            parse("\"abc: 1"),
+            Err(Error::UnterminatedString(position(0, 1, 1)))
+        );
+        assert_eq!(
+            parse("\"a\\q\": 1"),
+            Err(Error::InvalidEscape(position(2, 1, 3)))
+        );
+        assert_eq!(
+            parse("\"a\": 1\na: 2"),
+            Err(Error::DuplicateKey {
+                key: "a".to_string(),
+                first: position(0, 1, 1),
+                second: position(7, 2, 1),
+            })
+        );
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use indoc::indoc;
+    #[test]
+    fn dotted_keys() {
+        let dotted = fixtures::DOTTED_KEYS;
+        let nested = fixtures::DOTTED_KEYS_NESTED;
 
-    fn unwrap_object<'a>(input: &'a str) -> HashMap<&'a str, Value<'a>> {
-        match parse(input).unwrap() {
-            (_, Value::Object(o)) => o,
-            _ => panic!("not an object"),
-        }
+        assert_eq!(parse(dotted), parse(nested));
     }
 
-    fn unwrap_array<'a>(input: &'a str) -> Vec<Value<'a>> {
-        match parse(input).unwrap() {
-            (_, Value::Array(a)) => a,
-            _ => panic!("not an object"),
-        }
+    #[test]
+    fn dotted_key_comments() {
+        let input = "# leading\na.b: 1 # trailing\n";
+        let (_, comments) = parse_with_comments(input).unwrap();
+
+        assert_eq!(
+            comments.get(&[PathSegment::Key("a".into()), PathSegment::Key("b".into())]),
+            [
+                Comment::new(" leading", CommentKind::Leading),
+                Comment::new(" trailing", CommentKind::Trailing),
+            ]
+        );
+        assert_eq!(comments.get(&[PathSegment::Key("a".into())]), []);
     }
 
     #[test]
-    fn nested() {
+    fn conflicting_keys() {
+        let conflict = |key: &str, first, second| Error::ConflictingKey {
+            key: key.to_string(),
+            first,
+            second,
+        };
+
+        assert_eq!(
+            parse("a: 1\na.b: 2"),
+            Err(conflict("a", position(0, 1, 1), position(5, 2, 1)))
+        );
+        assert_eq!(
+            parse("a.b: 1\na: 2"),
+            Err(conflict("a", position(0, 1, 1), position(7, 2, 1)))
+        );
+        assert_eq!(
+            parse("a.b.c: 1\na:\n  b: 2"),
+            Err(conflict("b", position(0, 1, 1), position(14, 3, 3)))
+        );
+        assert_eq!(
+            parse("a:\n  b: 1\na.b.c: 2"),
+            Err(conflict("a.b", position(5, 2, 3), position(10, 3, 1)))
+        );
+        assert_eq!(
+            parse("a: [1]\na.b: 2"),
+            Err(conflict("a", position(0, 1, 1), position(7, 2, 1)))
+        );
+    }
+
+    #[test]
+    fn duplicate_dotted_keys() {
+        assert_eq!(
+            parse("a.b: 1\na.b: 2"),
+            Err(Error::DuplicateKey {
+                key: "a.b".to_string(),
+                first: position(0, 1, 1),
+                second: position(7, 2, 1),
+            })
+        );
+        assert_eq!(
+            parse("a:\n  b: 1\na.b: 2"),
+            Err(Error::DuplicateKey {
+                key: "a.b".to_string(),
+                first: position(5, 2, 3),
+                second: position(10, 3, 1),
+            })
+        );
+
+        let input = "a.b: 1\na.b: 2\na:\n  b: 3\n  c: 4\n";
+        let parse_keeping = |duplicate_keys| {
+            ParseOptions::new()
+                .duplicate_keys(duplicate_keys)
+                .parse(input)
+        };
+        assert_eq!(
+            parse_keeping(DuplicateKeys::KeepFirst),
+            parse("a:\n  b: 1\n  c: 4\n")
+        );
+        assert_eq!(
+            parse_keeping(DuplicateKeys::KeepLast),
+            parse("a:\n  b: 3\n  c: 4\n")
+        );
+
+        // The keys in the thrown away value don't count as defined.
+        let input = "a:\n  x: 1\na:\n  y: 2\na.y: 3\n";
+        assert_eq!(
+            ParseOptions::new()
+                .duplicate_keys(DuplicateKeys::KeepFirst)
+                .parse(input),
+            parse("a:\n  x: 1\n  y: 3\n")
+        );
+    }
+
+    #[test]
+    fn invalid_object() {
         let input = indoc! {r#"
-            key_1: 123
-            obj:
-                nested: 456
+            key_1:
+            x
         "#};
 
-        let mut obj = HashMap::new();
-        obj.insert("nested", Value::Number(456.0));
+        // `key_1` is null, so `x` is the next key.
+        assert_eq!(parse(input), Err(Error::MissingColon(position(8, 2, 2))));
+    }
+
+    #[test]
+    fn missing_value() {
+        let input = "key_1: ";
+
+        let mut expected = Map::new();
+        expected.insert("key_1".into(), Value::Null);
+
+        assert_eq!(unwrap_object(input), expected);
+    }
 
-        let mut expected = HashMap::new();
-        expected.insert("key_1", Value::Number(123.0));
-        expected.insert("obj", Value::Object(obj));
+    #[test]
+    fn null() {
+        let input = fixtures::NULL;
+
+        let mut expected = Map::new();
+        expected.insert("a".into(), Value::Null);
+        expected.insert("b".into(), Value::Null);
+        expected.insert("c".into(), Value::Null);
+        expected.insert("d".into(), Value::Null);
+        let mut e = Map::new();
+        e.insert("f".into(), Value::Null);
+        expected.insert("e".into(), Value::Object(e));
+        expected.insert(
+            "g".into(),
+            Value::Array(vec![Value::Null, Value::Null, Value::Null]),
+        );
+        expected.insert("h".into(), Value::Integer(1));
 
         assert_eq!(unwrap_object(input), expected);
     }
 
     #[test]
-    fn nested_double() {
-        let input = indoc! {r#"
-            key_1: 123
-            obj:
-                nested: 456
-                    nested_again: 789
-        "#};
+    fn unclosed_string() {
+        let input = r#"key_1: "foo"#;
 
-        let mut obj = HashMap::new();
-        obj.insert("nested", Value::Number(456.0));
+        assert_eq!(
+            parse(input),
+            Err(Error::UnterminatedString(position(7, 1, 8)))
+        );
+    }
+
+    #[test]
+    fn string_escapes() {
+        let input = r#"key_1: "a \"quoted\" \\ string\n\twith \u{1F600} escapes""#;
 
-        let mut expected = HashMap::new();
-        expected.insert("key_1", Value::Number(123.0));
-        expected.insert("obj", Value::Object(obj));
+        let mut expected = Map::new();
+        expected.insert(
+            "key_1".into(),
+            Value::String("a \"quoted\" \\ string\n\twith \u{1F600} escapes".into()),
+        );
 
         assert_eq!(unwrap_object(input), expected);
     }
 
     #[test]
-    fn nested_then_unnested() {
-        let input = indoc! {r#"
-            key_1: 123
-            obj:
-                nested: 456
-            top_level: 789
-        "#};
+    fn string_without_escapes_is_borrowed() {
+        let input = r#"key_1: "no escapes here""#;
+
+        match unwrap_object(input).remove("key_1") {
+            Some(Value::String(Cow::Borrowed(s))) => assert_eq!(s, "no escapes here"),
+            v => panic!("expected a borrowed string, got {:?}", v),
+        }
+    }
 
-        let mut obj = HashMap::new();
-        obj.insert("nested", Value::Number(456.0));
+    #[test]
+    fn empty_string() {
+        let input = r#"key_1: """#;
 
-        let mut expected = HashMap::new();
-        expected.insert("key_1", Value::Number(123.0));
-        expected.insert("obj", Value::Object(obj));
-        expected.insert("top_level", Value::Number(789.0));
+        let mut expected = Map::new();
+        expected.insert("key_1".into(), Value::String("".into()));
 
         assert_eq!(unwrap_object(input), expected);
     }
 
     #[test]
-    fn big_array() {
-        let input = indoc! {r#"
-            - 123
-            - "a string!"
-            - 3.14
-            - true
-            - false
-        "#};
+    fn invalid_escape() {
+        let input = r#"key_1: "foo \q bar""#;
 
-        let expected = vec![
-            Value::Number(123.0),
-            Value::String("a string!"),
-            Value::Number(3.14),
-            Value::Bool(true),
-            Value::Bool(false),
-        ];
+        assert_eq!(parse(input), Err(Error::InvalidEscape(position(12, 1, 13))));
+    }
 
-        assert_eq!(unwrap_array(input), expected);
+    #[test]
+    fn invalid_unicode_escape() {
+        assert!(parse(r#"key_1: "\u{}""#).is_err());
+        assert!(parse(r#"key_1: "\u{D800}""#).is_err());
+        assert!(parse(r#"key_1: "\u{1234567}""#).is_err());
     }
 
     #[test]
-    fn big_object() {
-        let input = indoc! {r#"
-            key_1: 123
-            keytwo: "a string!"
-            afloat: 3.14
-            truthy: true
-            falsey: false"#};
-
-        let mut expected = HashMap::new();
-        expected.insert("key_1", Value::Number(123.0));
-        expected.insert("keytwo", Value::String("a string!"));
-        expected.insert("afloat", Value::Number(3.14));
-        expected.insert("truthy", Value::Bool(true));
-        expected.insert("falsey", Value::Bool(false));
+    fn unterminated_string() {
+        let input = "key_1: \"foo\nkey_2: 1";
+
+        assert_eq!(
+            parse(input),
+            Err(Error::UnterminatedString(position(7, 1, 8)))
+        );
+    }
+
+    #[test]
+    fn raw_string() {
+        let input = fixtures::RAW_STRING;
+
+        let object = unwrap_object(input);
+
+        for (key, expected) in &[
+            ("path", r#"C:\Users\odin"#),
+            ("regex", r#"^\d+\.\d+$"#),
+            ("quotes", r#""double" quotes"#),
+            ("empty", ""),
+        ] {
+            match &object[*key] {
+                Value::String(Cow::Borrowed(s)) => assert_eq!(s, expected),
+                v => panic!("expected a borrowed string, got {:?}", v),
+            }
+        }
+    }
+
+    #[test]
+    fn unterminated_raw_string() {
+        let input = "key_1: 'foo\nkey_2: 1";
+
+        assert_eq!(
+            parse(input),
+            Err(Error::UnterminatedRawString(position(7, 1, 8)))
+        );
+    }
+
+    #[test]
+    fn block_string_literal() {
+        let input = fixtures::BLOCK_STRING_LITERAL;
+
+        let mut expected = Map::new();
+        expected.insert(
+            "sql".into(),
+            Value::String("SELECT *\nFROM users\n  WHERE id = 1\n".into()),
+        );
+        expected.insert("next".into(), Value::Integer(1));
 
         assert_eq!(unwrap_object(input), expected);
     }
 
     #[test]
-    fn it_works() {
-        let input = indoc! {r#"
-            key_1: 123
-            key_2: "a string!"
-            a_float: 3.14
-            truthy: true
-            falsey: false
-            obj:
-                nested: 456
-        "#};
+    fn block_string_folded() {
+        let input = fixtures::BLOCK_STRING_FOLDED;
+
+        let mut expected = Map::new();
+        expected.insert(
+            "text".into(),
+            Value::String("a long line\nanother paragraph\n".into()),
+        );
+
+        assert_eq!(unwrap_object(input), expected);
+    }
+
+    #[test]
+    fn block_string_folded_more_indented() {
+        for (input, expected) in &[
+            ("a: >\n  a\n    b\n  c\n", "a\n  b\nc\n"),
+            (
+                "a: >\n  a\n  b\n    c\n    d\n  e\n  f\n",
+                "a b\n  c\n  d\ne f\n",
+            ),
+            ("a: >\n  a\n\n    b\n\n  c\n", "a\n\n  b\n\nc\n"),
+        ] {
+            let mut expected_object = Map::new();
+            expected_object.insert("a".into(), Value::String((*expected).into()));
+
+            assert_eq!(unwrap_object(input), expected_object, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn block_string_chomping() {
+        let block = "  line 1\n  line 2\n\n\n";
+
+        for (header, expected) in &[
+            ("|", "line 1\nline 2\n"),
+            ("|-", "line 1\nline 2"),
+            ("|+", "line 1\nline 2\n\n\n"),
+            (">", "line 1 line 2\n"),
+            (">-", "line 1 line 2"),
+            (">+", "line 1 line 2\n\n\n"),
+        ] {
+            let input = format!("key: {}\n{}", header, block);
+
+            let mut expected_object = Map::new();
+            expected_object.insert("key".into(), Value::String((*expected).into()));
+
+            assert_eq!(unwrap_object(&input), expected_object, "{}", header);
+        }
+    }
 
-        let mut obj = HashMap::new();
-        obj.insert("nested", Value::Number(456.0));
+    #[test]
+    fn block_string_empty() {
+        let input = fixtures::BLOCK_STRING_EMPTY;
 
-        let mut expected = HashMap::new();
-        expected.insert("key_1", Value::Number(123.0));
-        expected.insert("key_2", Value::String("a string!"));
-        expected.insert("a_float", Value::Number(3.14));
-        expected.insert("truthy", Value::Bool(true));
-        expected.insert("falsey", Value::Bool(false));
-        expected.insert("obj", Value::Object(obj));
+        let mut expected = Map::new();
+        expected.insert("key_1".into(), Value::String("".into()));
+        expected.insert("key_2".into(), Value::String("".into()));
 
         assert_eq!(unwrap_object(input), expected);
     }
 
     #[test]
-    fn simple_array() {
-        let input = indoc! {r#"
-            - 1
-            - 2
-        "#};
+    fn block_string_in_array() {
+        let input = "- |\n  foo\n  bar\n- 1\n";
 
-        let expected = vec![Value::Number(1.0), Value::Number(2.0)];
+        let expected = vec![Value::String("foo\nbar\n".into()), Value::Integer(1)];
 
         assert_eq!(unwrap_array(input), expected);
     }
 
     #[test]
-    fn simple_object() {
-        let input = indoc! {r#"
-            keyone: 123
-            keytwo: 456"#};
+    fn block_string_invalid_header() {
+        assert!(parse("key: |x\n  foo").is_err());
+    }
 
-        let mut expected = HashMap::new();
-        expected.insert("keyone", Value::Number(123.0));
-        expected.insert("keytwo", Value::Number(456.0));
+    #[test]
+    fn comments() {
+        let input = fixtures::COMMENTS;
+
+        let mut obj = Map::new();
+        obj.insert("nested".into(), Value::String("# not a comment".into()));
+
+        let mut expected = Map::new();
+        expected.insert("key_1".into(), Value::Integer(123));
+        expected.insert("obj".into(), Value::Object(obj));
+        expected.insert("arr".into(), Value::Array(vec![Value::Integer(1)]));
+
+        let (value, comments) = parse_with_comments(input).unwrap();
+        assert_eq!(value, Value::Object(expected));
+
+        use CommentKind::*;
+        use PathSegment::*;
+
+        assert_eq!(
+            comments.get(&[Key("key_1".into())]),
+            &[
+                Comment::new(" Leading comment", Leading),
+                Comment::new(" Trailing comment", Trailing)
+            ]
+        );
+        assert_eq!(
+            comments.get(&[Key("obj".into())]),
+            &[Comment::new(" On a collection", Trailing)]
+        );
+        assert_eq!(
+            comments.get(&[Key("obj".into()), Key("nested".into())]),
+            &[
+                Comment::new(" Indented less than the next line", Leading),
+                Comment::new("no space", Trailing)
+            ]
+        );
+        assert_eq!(
+            comments.get(&[Key("arr".into())]),
+            &[Comment::new(" Before a dedent", Leading)]
+        );
+        assert_eq!(
+            comments.get(&[Key("arr".into()), Index(0)]),
+            &[Comment::new(" Indented more than the next line", Leading)]
+        );
+        assert_eq!(comments.get(&[]), &[Comment::new(" At the end", Trailing)]);
+        assert_eq!(comments.iter().count(), 6);
+    }
 
-        assert_eq!(unwrap_object(input), expected);
+    #[test]
+    fn comment_in_raw_and_block_strings() {
+        let input = fixtures::COMMENT_IN_RAW_AND_BLOCK_STRINGS;
+
+        let mut expected = Map::new();
+        expected.insert("raw".into(), Value::String("# not a comment".into()));
+        expected.insert("block".into(), Value::String("# not a comment\n".into()));
+
+        let (value, comments) = parse_with_comments(input).unwrap();
+        assert_eq!(value, Value::Object(expected));
+        assert!(comments.is_empty());
     }
 
     #[test]
-    fn array_then_object() {
+    fn comment_after_block_string_header() {
+        let input = fixtures::COMMENT_AFTER_BLOCK_STRING_HEADER;
+
+        let mut expected = Map::new();
+        expected.insert("a".into(), Value::String("x\n".into()));
+        expected.insert("b".into(), Value::String("y".into()));
+
+        let (value, comments) = parse_with_comments(input).unwrap();
+        assert_eq!(value, Value::Object(expected));
+
+        use PathSegment::*;
+
+        assert_eq!(
+            comments.get(&[Key("a".into())]),
+            &[Comment::new(" note", CommentKind::Trailing)]
+        );
+        assert_eq!(
+            comments.get(&[Key("b".into())]),
+            &[Comment::new("tight", CommentKind::Trailing)]
+        );
+    }
+
+    #[test]
+    fn duplicate_keys() {
         let input = indoc! {r#"
-            - 1
-            foo: 2
+            a: 1
+            b:
+              a: 2
+            c:
+              - a: 3
+                b: 4
+            a: 5
         "#};
 
-        assert!(parse(input).is_err());
+        assert_eq!(
+            parse(input),
+            Err(Error::DuplicateKey {
+                key: "a".to_string(),
+                first: position(0, 1, 1),
+                second: position(36, 7, 1),
+            })
+        );
+
+        let input = "- a: 1\n  a: 2\n";
+
+        assert_eq!(
+            parse(input),
+            Err(Error::DuplicateKey {
+                key: "a".to_string(),
+                first: position(2, 1, 3),
+                second: position(9, 2, 3),
+            })
+        );
     }
 
     #[test]
-    fn empty_object() {
-        let input = "key_1:";
+    fn duplicate_keys_options() {
+        let input = indoc! {r#"
+            a: 1
+            b: 2
+            a: 3
+        "#};
 
-        assert!(parse(input).is_err());
+        let mut first = Map::new();
+        first.insert("a".into(), Value::Integer(1));
+        first.insert("b".into(), Value::Integer(2));
+
+        let mut last = Map::new();
+        last.insert("a".into(), Value::Integer(3));
+        last.insert("b".into(), Value::Integer(2));
+
+        assert_eq!(
+            ParseOptions::new()
+                .duplicate_keys(DuplicateKeys::KeepFirst)
+                .parse(input),
+            Ok(Value::Object(first))
+        );
+        assert_eq!(
+            ParseOptions::new()
+                .duplicate_keys(DuplicateKeys::KeepLast)
+                .parse(input),
+            Ok(Value::Object(last))
+        );
     }
 
     #[test]
-    fn empty() {
-        let input = "";
+    fn object_order() {
+        let input = fixtures::OBJECT_ORDER;
+
+        let object = unwrap_object(input);
+        assert_eq!(
+            object.keys().collect::<Vec<_>>(),
+            ["zebra", "apple", "kiwi", "apple_2"]
+        );
+
+        match &object["apple"] {
+            Value::Object(apple) => assert_eq!(
+                apple.keys().collect::<Vec<_>>(),
+                ["mango", "banana", "cherry"]
+            ),
+            v => panic!("expected an object, got {:?}", v),
+        }
+    }
 
-        assert!(parse(input).is_err());
+    #[test]
+    fn object_order_duplicate_keep_last() {
+        let input = "b: 1\na: 2\nb: 3\n";
+
+        let object = ParseOptions::new()
+            .duplicate_keys(DuplicateKeys::KeepLast)
+            .parse(input)
+            .unwrap();
+
+        match object {
+            Value::Object(object) => assert_eq!(
+                object.into_iter().collect::<Vec<_>>(),
+                [
+                    ("b".into(), Value::Integer(3)),
+                    ("a".into(), Value::Integer(2))
+                ]
+            ),
+            v => panic!("expected an object, got {:?}", v),
+        }
     }
 
     #[test]
-    fn missing_colon() {
-        let input = "key_1";
+    fn integers_and_floats() {
+        let input = fixtures::INTEGERS_AND_FLOATS;
 
-        assert!(parse(input).is_err());
+        let mut expected = Map::new();
+        expected.insert("int".into(), Value::Integer(1));
+        expected.insert("float".into(), Value::Float(1.0));
+        expected.insert("negative".into(), Value::Integer(-42));
+        expected.insert("big".into(), Value::Integer(9_007_199_254_740_993));
+        expected.insert("max".into(), Value::Integer(i64::MAX));
+        expected.insert("min".into(), Value::Integer(i64::MIN));
+
+        assert_eq!(unwrap_object(input), expected);
     }
 
     #[test]
-    fn key_with_spaces() {
-        let input = "foo bar: 123";
+    fn integer_overflow() {
+        let input = "key_1: 9223372036854775808";
+
+        assert_eq!(
+            parse(input),
+            Err(Error::InvalidNumber(
+                position(7, 1, 8),
+                NumberError::Overflow
+            ))
+        );
+    }
 
-        let mut expected = HashMap::new();
-        expected.insert("foo bar", Value::Number(123.0));
+    #[test]
+    fn integer_followed_by_letters() {
+        let input = "key_1: 10px";
+
+        assert_eq!(
+            parse(input),
+            Err(Error::InvalidNumber(
+                position(9, 1, 10),
+                NumberError::TrailingCharacters
+            ))
+        );
+    }
 
-        assert_eq!(unwrap_object(input), expected);
+    #[test]
+    fn number_accessors() {
+        assert_eq!(Value::Integer(-1).as_i64(), Ok(-1));
+        assert_eq!(Value::Float(-1.0).as_i64(), Ok(-1));
+        assert_eq!(
+            Value::Float(1.5).as_i64(),
+            Err(ConversionError::Lossy("i64"))
+        );
+        assert_eq!(
+            Value::Float(1e19).as_i64(),
+            Err(ConversionError::Lossy("i64"))
+        );
+        assert_eq!(Value::Bool(true).as_i64(), Err(ConversionError::NotANumber));
+
+        assert_eq!(Value::Integer(1).as_u64(), Ok(1));
+        assert_eq!(Value::Float(1e19).as_u64(), Ok(10_000_000_000_000_000_000));
+        assert_eq!(
+            Value::Integer(-1).as_u64(),
+            Err(ConversionError::Lossy("u64"))
+        );
+
+        assert_eq!(Value::Float(1.5).as_f64(), Ok(1.5));
+        assert_eq!(
+            Value::Integer(1 << 53).as_f64(),
+            Ok(9_007_199_254_740_992.0)
+        );
+        assert_eq!(
+            Value::Integer(1 << 60).as_f64(),
+            Ok(1_152_921_504_606_846_976.0)
+        );
+        assert_eq!(Value::Integer(i64::MIN).as_f64(), Ok(-(2f64.powi(63))));
+        assert_eq!(
+            Value::Integer((1 << 53) + 1).as_f64(),
+            Err(ConversionError::Lossy("f64"))
+        );
+        assert_eq!(
+            Value::Integer(i64::MAX).as_f64(),
+            Err(ConversionError::Lossy("f64"))
+        );
+        assert_eq!(
+            Value::String("1".into()).as_f64(),
+            Err(ConversionError::NotANumber)
+        );
     }
 
     #[test]
-    fn key_with_spaces_and_missing_colon() {
-        let input = "foo bar";
+    fn invalid_float() {
+        let input = "key_1: 3.1.4";
 
-        assert!(parse(input).is_err());
+        assert_eq!(
+            parse(input),
+            Err(Error::InvalidNumber(
+                position(10, 1, 11),
+                NumberError::MultipleDecimalPoints
+            ))
+        );
     }
 
+    /// The number grammar, as numbers it accepts and the reason and offset in the number it
+    /// rejects the others with.
     #[test]
-    fn invalid_object() {
-        let input = indoc! {r#"
-            key_1:
-            x
-        "#};
+    fn number_grammar() {
+        let valid = [
+            ("0", Value::Integer(0)),
+            ("-0", Value::Integer(0)),
+            ("42", Value::Integer(42)),
+            ("-17", Value::Integer(-17)),
+            ("1_000_000", Value::Integer(1_000_000)),
+            ("0.5", Value::Float(0.5)),
+            ("-0.0", Value::Float(-0.0)),
+            ("-3.25", Value::Float(-3.25)),
+            ("10.000_001", Value::Float(10.000_001)),
+            ("0x1F", Value::Integer(31)),
+            ("0xdead_beef", Value::Integer(0xdead_beef)),
+            ("0o17", Value::Integer(15)),
+            ("0b1010_1010", Value::Integer(170)),
+            ("0x7FFF_FFFF_FFFF_FFFF", Value::Integer(i64::MAX)),
+            ("0x0", Value::Integer(0)),
+        ];
+        for (number, expected) in valid {
+            assert_eq!(
+                parse(&format!("n: {}", number)).map(|v| match v {
+                    Value::Object(mut object) => object.remove("n"),
+                    _ => None,
+                }),
+                Ok(Some(expected)),
+                "{}",
+                number
+            );
+        }
 
-        assert!(parse(input).is_err());
+        let invalid = [
+            ("+1", 0, NumberError::LeadingPlus),
+            ("+.5", 0, NumberError::LeadingPlus),
+            ("+inf", 0, NumberError::LeadingPlus),
+            ("01", 0, NumberError::LeadingZero),
+            ("0_1", 0, NumberError::LeadingZero),
+            ("-007", 1, NumberError::LeadingZero),
+            (".5", 0, NumberError::MissingIntegerDigits),
+            ("-.5", 1, NumberError::MissingIntegerDigits),
+            ("1.", 1, NumberError::MissingFractionDigits),
+            ("1.e5", 1, NumberError::MissingFractionDigits),
+            ("1._5", 1, NumberError::MissingFractionDigits),
+            ("3.1.4", 3, NumberError::MultipleDecimalPoints),
+            ("1e5", 1, NumberError::Exponent),
+            ("1.5E-3", 3, NumberError::Exponent),
+            ("1__000", 1, NumberError::MisplacedUnderscore),
+            ("1_", 1, NumberError::MisplacedUnderscore),
+            ("1_.5", 1, NumberError::MisplacedUnderscore),
+            ("1.5_", 3, NumberError::MisplacedUnderscore),
+            ("0x_1", 2, NumberError::MisplacedUnderscore),
+            ("-0x10", 0, NumberError::SignedPrefix),
+            ("0x", 2, NumberError::MissingDigits),
+            ("0b", 2, NumberError::MissingDigits),
+            ("0xg", 2, NumberError::InvalidDigit),
+            ("0b102", 4, NumberError::InvalidDigit),
+            ("0o8", 2, NumberError::InvalidDigit),
+            ("inf", 0, NumberError::NonFinite),
+            ("-inf", 0, NumberError::NonFinite),
+            ("Infinity", 0, NumberError::NonFinite),
+            ("NaN", 0, NumberError::NonFinite),
+            ("9223372036854775808", 0, NumberError::Overflow),
+            ("-9223372036854775809", 0, NumberError::Overflow),
+            ("0x8000_0000_0000_0000", 0, NumberError::Overflow),
+            (&format!("1{}.0", "0".repeat(400)), 0, NumberError::Overflow),
+            ("10px", 2, NumberError::TrailingCharacters),
+            ("0X10", 1, NumberError::TrailingCharacters),
+            ("0x1.5", 3, NumberError::TrailingCharacters),
+        ];
+        for (number, offset, reason) in invalid {
+            assert_eq!(
+                parse(&format!("n: {}", number)),
+                Err(Error::InvalidNumber(
+                    position(3 + offset, 1, 4 + offset),
+                    reason
+                )),
+                "{}",
+                number
+            );
+        }
+
+        // Words that only start like `inf` or `nan` aren't numbers at all.
+        assert_eq!(
+            parse("n: info"),
+            Err(Error::ExpectedValue(position(3, 1, 4)))
+        );
+        assert_eq!(parse("n: -"), Err(Error::ExpectedValue(position(3, 1, 4))));
+    }
+
+    /// Parses `n: {value}` and returns the value, with the positions of date, time, duration and
+    /// byte size errors relative to the value.
+    fn parse_value(value: &str) -> Result<Value<'static>, Error> {
+        // Leaked so the value can borrow from it.
+        let input: &'static str = Box::leak(format!("n: {}", value).into_boxed_str());
+        let relative = |p: Position| position(p.offset - 3, p.line, p.column - 3);
+
+        match parse(input) {
+            Ok(Value::Object(mut object)) => Ok(object.swap_remove("n").unwrap()),
+            Ok(v) => panic!("expected an object, got {:?}", v),
+            Err(Error::InvalidDateTime(p)) => Err(Error::InvalidDateTime(relative(p))),
+            Err(Error::InvalidDuration(p)) => Err(Error::InvalidDuration(relative(p))),
+            Err(Error::InvalidByteSize(p, reason)) => {
+                Err(Error::InvalidByteSize(relative(p), reason))
+            }
+            Err(e) => Err(e),
+        }
     }
 
     #[test]
-    fn missing_value() {
-        let input = "key_1: ";
+    fn dates_and_times() {
+        let date = |year, month, day| Date::new(year, month, day).unwrap();
+        let time =
+            |hour, minute, second, nanosecond| Time::new(hour, minute, second, nanosecond).unwrap();
+
+        let valid = [
+            ("2024-02-29", Value::Date(date(2024, 2, 29))),
+            ("0000-01-01", Value::Date(date(0, 1, 1))),
+            ("09:30:00", Value::Time(time(9, 30, 0, 0))),
+            ("23:59:60.123", Value::Time(time(23, 59, 60, 123_000_000))),
+            ("00:00:00.000000001", Value::Time(time(0, 0, 0, 1))),
+            (
+                "2024-02-29T09:30:00",
+                Value::DateTime(DateTime {
+                    date: date(2024, 2, 29),
+                    time: time(9, 30, 0, 0),
+                    offset: None,
+                }),
+            ),
+            (
+                "2024-02-29t09:30:00z",
+                Value::DateTime(DateTime {
+                    date: date(2024, 2, 29),
+                    time: time(9, 30, 0, 0),
+                    offset: Some(Offset::UTC),
+                }),
+            ),
+            (
+                "2024-02-29T09:30:00+00:00",
+                Value::DateTime(DateTime {
+                    date: date(2024, 2, 29),
+                    time: time(9, 30, 0, 0),
+                    offset: Some(Offset::UTC),
+                }),
+            ),
+            (
+                "2024-02-29 09:30:00.5+05:30",
+                Value::DateTime(DateTime {
+                    date: date(2024, 2, 29),
+                    time: time(9, 30, 0, 500_000_000),
+                    offset: Offset::new(330),
+                }),
+            ),
+            (
+                "2024-02-29T09:30:00-00:45",
+                Value::DateTime(DateTime {
+                    date: date(2024, 2, 29),
+                    time: time(9, 30, 0, 0),
+                    offset: Offset::new(-45),
+                }),
+            ),
+        ];
+        for (input, expected) in valid {
+            assert_eq!(parse_value(input), Ok(expected), "{}", input);
+        }
 
-        assert!(parse(input).is_err());
+        for input in [
+            "2023-02-29",
+            "2024-13-01",
+            "2024-1-01",
+            "2024-01-01x",
+            "2024-01-01T",
+            "2024-01-01T10:00",
+            "2024-01-01T10:00:00+24:00",
+            "2024-01-01T10:00:00-00:00",
+            "2024-01-01T10:00:00+0100",
+            "2024-01-01T10:00:00Zx",
+            "24:00:00",
+            "12:30",
+            "12:30:00.",
+            "12:30:00.1234567891",
+            "12:30:00Z",
+        ] {
+            assert_eq!(
+                parse_value(input),
+                Err(Error::InvalidDateTime(position(0, 1, 1))),
+                "{}",
+                input
+            );
+        }
+
+        let input = fixtures::DATES_AND_TIMES;
+        let object = unwrap_object(input);
+        assert_eq!(
+            object["dates"],
+            Value::Array(vec![
+                Value::Date(date(2024, 1, 1)),
+                Value::Time(time(12, 0, 0, 0))
+            ])
+        );
+        assert_eq!(
+            object["times"],
+            Value::Array(vec![Value::Time(time(12, 30, 0, 0))])
+        );
     }
 
     #[test]
-    fn unclosed_string() {
-        let input = r#"key_1: "foo"#;
+    fn durations() {
+        let valid = [
+            ("30s", Duration::from_secs(30)),
+            ("5m", Duration::from_secs(300)),
+            ("1h30m", Duration::from_secs(5400)),
+            ("1d2h3m4s5ms6us7ns", Duration::new(93_784, 5_006_007)),
+            ("0.5s", Duration::from_millis(500)),
+            ("1.5h", Duration::from_secs(5400)),
+            ("250ms", Duration::from_millis(250)),
+            ("10µs", Duration::from_micros(10)),
+            ("1_000ms", Duration::from_secs(1)),
+            ("0s", Duration::from_secs(0)),
+            ("0.000000001s", Duration::from_nanos(1)),
+        ];
+        for (input, expected) in valid {
+            assert_eq!(
+                parse_value(input),
+                Ok(Value::Duration(expected)),
+                "{}",
+                input
+            );
+        }
 
-        assert!(parse(input).is_err());
+        let invalid = [
+            ("1m1h", 2),
+            ("1s1s", 2),
+            ("1us1µs", 3),
+            ("1h30", 2),
+            ("1h30x", 2),
+            ("5min", 0),
+            ("1.5ns", 0),
+            ("0.0000000001s", 0),
+            ("213503982334602d", 0),
+        ];
+        for (input, offset) in invalid {
+            assert_eq!(
+                parse_value(input),
+                Err(Error::InvalidDuration(position(offset, 1, offset + 1))),
+                "{}",
+                input
+            );
+        }
+
+        // Anything without a unit is left to be a number.
+        assert_eq!(parse_value("10"), Ok(Value::Integer(10)));
+        assert_eq!(
+            parse_value("10px"),
+            Err(Error::InvalidNumber(
+                position(5, 1, 6),
+                NumberError::TrailingCharacters
+            ))
+        );
     }
 
     #[test]
-    fn invalid_float() {
-        let input = "key_1: 3.1.4";
+    fn byte_sizes() {
+        let valid = [
+            ("0B", 0),
+            ("512B", 512),
+            ("512KiB", 512 * 1024),
+            ("10MB", 10_000_000),
+            ("10kB", 10_000),
+            ("10KB", 10_000),
+            ("1.5GiB", 3 << 29),
+            ("1.5KB", 1500),
+            ("1_000MB", 1_000_000_000),
+            ("15EiB", 15 << 60),
+            ("18446744073709551615B", u64::MAX),
+        ];
+        for (input, expected) in valid {
+            assert_eq!(parse_value(input), Ok(Value::Bytes(expected)), "{}", input);
+        }
 
-        assert!(parse(input).is_err());
+        let invalid = [
+            ("10Mb", 2, ByteSizeError::UnknownUnit),
+            ("10mb", 2, ByteSizeError::UnknownUnit),
+            ("1Gib", 1, ByteSizeError::UnknownUnit),
+            ("8b", 1, ByteSizeError::UnknownUnit),
+            ("1.5B", 0, ByteSizeError::Fractional),
+            ("0.1KiB", 0, ByteSizeError::Fractional),
+            ("16EiB", 0, ByteSizeError::Overflow),
+            ("18446744073709551616B", 0, ByteSizeError::Overflow),
+        ];
+        for (input, offset, reason) in invalid {
+            assert_eq!(
+                parse_value(input),
+                Err(Error::InvalidByteSize(
+                    position(offset, 1, offset + 1),
+                    reason
+                )),
+                "{}",
+                input
+            );
+        }
+
+        // Prefixed integers, durations and other suffixes aren't byte sizes.
+        assert_eq!(parse_value("0b1010"), Ok(Value::Integer(10)));
+        assert_eq!(
+            parse_value("5ms"),
+            Ok(Value::Duration(Duration::from_millis(5)))
+        );
+        assert_eq!(
+            parse_value("10MBx"),
+            Err(Error::InvalidNumber(
+                position(5, 1, 6),
+                NumberError::TrailingCharacters
+            ))
+        );
+
+        assert_eq!(Value::Bytes(1024).as_byte_size(), Ok(1024));
+        assert_eq!(
+            Value::Integer(1024).as_byte_size(),
+            Err(ConversionError::NotAByteSize)
+        );
+    }
+
+    #[test]
+    fn time_accessors() {
+        assert_eq!(
+            Value::Duration(Duration::from_secs(1)).as_duration(),
+            Ok(Duration::from_secs(1))
+        );
+        assert_eq!(
+            Value::Integer(1).as_duration(),
+            Err(ConversionError::NotADuration)
+        );
+
+        let date_time = match parse_value("1970-01-01T00:01:00+00:01").unwrap() {
+            Value::DateTime(date_time) => date_time,
+            v => panic!("expected a date-time, got {:?}", v),
+        };
+        assert_eq!(
+            Value::DateTime(date_time).as_system_time(),
+            Ok(std::time::UNIX_EPOCH)
+        );
+        assert_eq!(
+            Value::DateTime(DateTime {
+                offset: None,
+                ..date_time
+            })
+            .as_system_time(),
+            Err(ConversionError::NoOffset)
+        );
+        assert_eq!(
+            Value::Date(date_time.date).as_system_time(),
+            Err(ConversionError::NotADateTime)
+        );
+    }
+
+    #[test]
+    fn too_deep() {
+        let input = format!("{}1", "- ".repeat(MAX_DEPTH));
+        assert!(parse(&input).is_ok());
+
+        let input = format!("{}1", "- ".repeat(MAX_DEPTH + 1));
+        assert_eq!(
+            parse(&input),
+            Err(Error::TooDeep(position(
+                MAX_DEPTH * 2,
+                1,
+                MAX_DEPTH * 2 + 1
+            )))
+        );
+
+        let input = (0..1_000)
+            .map(|i| format!("{}a:\n", " ".repeat(i)))
+            .collect::<String>();
+        assert!(matches!(parse(&input), Err(Error::TooDeep(_))));
+
+        let input = format!("a: {}", "[".repeat(100_000));
+        assert!(matches!(parse(&input), Err(Error::TooDeep(_))));
+
+        let input = format!("{}b: 1", "a.".repeat(100_000));
+        assert!(matches!(parse(&input), Err(Error::TooDeep(_))));
+    }
+
+    #[test]
+    fn flow_collections() {
+        let flow = fixtures::FLOW_COLLECTIONS;
+        let block = fixtures::FLOW_COLLECTIONS_BLOCK;
+
+        assert_eq!(parse(flow), parse(block));
+        assert_eq!(
+            unwrap_object(flow)["ports"],
+            Value::Array(vec![Value::Integer(80), Value::Integer(443)])
+        );
+    }
+
+    #[test]
+    fn flow_errors() {
+        assert_eq!(
+            parse("a: [1, 2"),
+            Err(Error::UnclosedFlow(position(3, 1, 4)))
+        );
+        assert_eq!(
+            parse("a: { b: [1] # }"),
+            Err(Error::UnclosedFlow(position(3, 1, 4)))
+        );
+        assert_eq!(
+            parse("a: [1,\n  2]"),
+            Err(Error::UnclosedFlow(position(3, 1, 4)))
+        );
+        assert_eq!(
+            parse("a: [1 2]"),
+            Err(Error::UnexpectedToken(position(6, 1, 7)))
+        );
+        assert_eq!(
+            parse("a: [1, , 2]"),
+            Err(Error::ExpectedValue(position(7, 1, 8)))
+        );
+        assert_eq!(parse("a: {b}"), Err(Error::MissingColon(position(5, 1, 6))));
+        assert_eq!(
+            parse("a: {b: 1, b: 2}"),
+            Err(Error::DuplicateKey {
+                key: "b".to_string(),
+                first: position(4, 1, 5),
+                second: position(10, 1, 11),
+            })
+        );
+        assert_eq!(
+            parse("a: [1] 2"),
+            Err(Error::UnexpectedToken(position(7, 1, 8)))
+        );
+    }
+
+    mod never_panics {
+        use super::*;
+        use proptest::prelude::*;
+
+        proptest! {
+            #[test]
+            fn arbitrary_bytes(bytes in proptest::collection::vec(any::<u8>(), 0..512)) {
+                let _ = parse(&String::from_utf8_lossy(&bytes)).map_err(|e| e.to_string());
+            }
+
+            #[test]
+            fn syntax_characters(input in "[ \t\n#:'\"\\\\|>+\\-_.a-z0-9{}\\[\\],~é]{0,256}") {
+                let _ = parse(&input).map_err(|e| e.to_string());
+            }
+        }
     }
 }
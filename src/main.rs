@@ -0,0 +1,698 @@
+//! The `ooml` command, which checks, formats, converts and queries ooml files.
+
+use std::borrow::Cow;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+
+use ooml::{Diagnostic, Error, PathSegment, Value};
+
+const USAGE: &str = "\
+usage: ooml [--format=text|json] <command> [<args>]
+
+commands:
+    check [FILE...]          check that files parse, printing an error for each that doesn't
+    fmt [--check] [FILE...]  format files in place, or with --check list those that aren't
+    to-json [FILE]           convert to JSON, with dates, times and durations as strings like
+                             `2024-01-31` and `5m30s`, and byte sizes as numbers of bytes
+    from-json [FILE]         convert JSON to ooml
+    get PATH [FILE]          print the value at a path like `servers[0].name`
+
+Standard input is read when no file is given, or for `-`. Errors are printed to standard error,
+as one JSON object per line with --format=json.";
+
+/// Everything went fine.
+const SUCCESS: i32 = 0;
+/// A file didn't parse or wasn't formatted, or a path had no value.
+const FAILURE: i32 = 1;
+/// The command line was wrong, or a file couldn't be read or written.
+const ERROR: i32 = 2;
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let stderr = io::stderr();
+
+    let mut cli = Cli {
+        format: Format::Text,
+        stdin: &mut stdin.lock(),
+        stdout: &mut stdout.lock(),
+        stderr: &mut stderr.lock(),
+    };
+    process::exit(cli.run(&args));
+}
+
+/// How errors are written.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    /// Diagnostics pointing at the error in the source.
+    Text,
+    /// One JSON object per line, with `file`, `line`, `column` and `message` keys.
+    Json,
+}
+
+struct Cli<'a> {
+    format: Format,
+    stdin: &'a mut dyn Read,
+    stdout: &'a mut dyn Write,
+    stderr: &'a mut dyn Write,
+}
+
+/// A file to read, or standard input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Input<'a> {
+    File(&'a str),
+    Stdin,
+}
+
+impl<'a> Input<'a> {
+    fn name(&self) -> &'a str {
+        match self {
+            Input::File(name) => name,
+            Input::Stdin => "<stdin>",
+        }
+    }
+}
+
+impl Cli<'_> {
+    /// Runs the command in `args`, returning the exit code.
+    fn run(&mut self, args: &[String]) -> i32 {
+        let mut rest = Vec::new();
+        for arg in args {
+            match arg.as_str() {
+                "--format=text" => self.format = Format::Text,
+                "--format=json" => self.format = Format::Json,
+                "-h" | "--help" => return self.usage(None),
+                arg => rest.push(arg),
+            }
+        }
+
+        let (command, args) = match rest.split_first() {
+            Some((command, args)) => (*command, args),
+            None => return self.usage(Some("no command given")),
+        };
+        let (flags, args): (Vec<&str>, Vec<&str>) = args
+            .iter()
+            .partition(|arg| arg.starts_with('-') && arg.len() > 1);
+
+        let mut check = false;
+        for flag in flags {
+            match (command, flag) {
+                ("fmt", "--check") => check = true,
+                _ => return self.usage(Some(&format!("unknown option `{}`", flag))),
+            }
+        }
+
+        let result = match command {
+            "check" => Ok(self.check(&inputs(&args))),
+            "fmt" => Ok(self.fmt(&inputs(&args), check)),
+            "to-json" => self
+                .input(command, &args)
+                .map(|input| self.convert_to_json(input)),
+            "from-json" => self
+                .input(command, &args)
+                .map(|input| self.convert_from_json(input)),
+            "get" => match args.split_first() {
+                Some((path, args)) => self.input(command, args).map(|input| self.get(path, input)),
+                None => Err(self.usage(Some("`get` needs a path"))),
+            },
+            _ => Err(self.usage(Some(&format!("unknown command `{}`", command)))),
+        };
+
+        match result {
+            Ok(code) | Err(code) => code,
+        }
+    }
+
+    /// The input of a command that takes at most one file.
+    fn input<'a>(&mut self, command: &str, args: &[&'a str]) -> Result<Input<'a>, i32> {
+        match inputs(args).as_slice() {
+            [input] => Ok(*input),
+            _ => Err(self.usage(Some(&format!("`{}` takes one file", command)))),
+        }
+    }
+
+    fn check(&mut self, inputs: &[Input<'_>]) -> i32 {
+        let mut code = SUCCESS;
+        for &input in inputs {
+            let result = self.read(input).and_then(|source| {
+                ooml::parse(&source)
+                    .map(drop)
+                    .map_err(|error| self.parse_error(input, &source, &error))
+            });
+            code = code.max(result.err().unwrap_or(SUCCESS));
+        }
+        code
+    }
+
+    /// Formats files in place and standard input to standard output, or with `check` lists the
+    /// inputs that aren't formatted.
+    fn fmt(&mut self, inputs: &[Input<'_>], check: bool) -> i32 {
+        let mut code = SUCCESS;
+        for &input in inputs {
+            let result = self.read(input).and_then(|source| {
+                let formatted = ooml::format(&source)
+                    .map_err(|error| self.parse_error(input, &source, &error))?;
+
+                match input {
+                    _ if check && formatted != source => {
+                        self.write(format!("{}\n", input.name()))?;
+                        Err(FAILURE)
+                    }
+                    _ if check => Ok(()),
+                    Input::Stdin => self.write(formatted),
+                    Input::File(_) if formatted == source => Ok(()),
+                    Input::File(name) => fs::write(name, formatted)
+                        .map_err(|error| self.error(Some(input), &error.to_string(), ERROR)),
+                }
+            });
+            code = code.max(result.err().unwrap_or(SUCCESS));
+        }
+        code
+    }
+
+    fn convert_to_json(&mut self, input: Input<'_>) -> i32 {
+        exit_code(self.read(input).and_then(|source| {
+            let value =
+                ooml::parse(&source).map_err(|error| self.parse_error(input, &source, &error))?;
+            let mut text =
+                serde_json::to_string_pretty(&to_json(&value)).expect("JSON values serialize");
+            text.push('\n');
+            self.write(text)
+        }))
+    }
+
+    fn convert_from_json(&mut self, input: Input<'_>) -> i32 {
+        exit_code(self.read(input).and_then(|source| {
+            let json = serde_json::from_str::<serde_json::Value>(&source).map_err(|error| {
+                let position = Some((error.line(), error.column()));
+                self.report(Some(input), position, &error.to_string(), None);
+                FAILURE
+            })?;
+            let text = ooml::to_string(&json)
+                .map_err(|error| self.error(Some(input), &error.to_string(), FAILURE))?;
+
+            self.write(text)
+        }))
+    }
+
+    /// Prints the value at `path`. Strings are printed as they are, without quotes, and other
+    /// values as ooml.
+    fn get(&mut self, path: &str, input: Input<'_>) -> i32 {
+        let path = match parse_path(path) {
+            Some(path) => path,
+            None => return self.usage(Some(&format!("invalid path `{}`", path))),
+        };
+
+        exit_code(self.read(input).and_then(|source| {
+            let value =
+                ooml::parse(&source).map_err(|error| self.parse_error(input, &source, &error))?;
+
+            let mut text = match lookup(&value, &path) {
+                Some(Value::String(string)) => string.to_string(),
+                Some(value) => value.to_string(),
+                None => {
+                    let message = format!("there's no value at `{}`", describe(&path));
+                    return Err(self.error(Some(input), &message, FAILURE));
+                }
+            };
+            if !text.ends_with('\n') {
+                text.push('\n');
+            }
+            self.write(text)
+        }))
+    }
+
+    fn read(&mut self, input: Input<'_>) -> Result<String, i32> {
+        let result = match input {
+            Input::File(name) => fs::read_to_string(name),
+            Input::Stdin => {
+                let mut source = String::new();
+                self.stdin.read_to_string(&mut source).map(|_| source)
+            }
+        };
+
+        result.map_err(|error| self.error(Some(input), &error.to_string(), ERROR))
+    }
+
+    fn write(&mut self, text: String) -> Result<(), i32> {
+        self.stdout
+            .write_all(text.as_bytes())
+            .map_err(|error| self.error(None, &error.to_string(), ERROR))
+    }
+
+    /// Reports an error parsing `source`, returning the exit code for it.
+    fn parse_error(&mut self, input: Input<'_>, source: &str, error: &Error) -> i32 {
+        let position = error.position().map(|p| (p.line, p.column));
+        let diagnostic = Diagnostic::new(error, source).file_name(input.name());
+        self.report(
+            Some(input),
+            position,
+            &diagnostic.message(),
+            Some(&diagnostic),
+        );
+        FAILURE
+    }
+
+    /// Reports an error without a position, returning `code`.
+    fn error(&mut self, input: Option<Input<'_>>, message: &str, code: i32) -> i32 {
+        self.report(input, None, message, None);
+        code
+    }
+
+    /// Writes an error to standard error, as the diagnostic if there is one for text.
+    fn report(
+        &mut self,
+        input: Option<Input<'_>>,
+        position: Option<(usize, usize)>,
+        message: &str,
+        diagnostic: Option<&Diagnostic<'_>>,
+    ) {
+        let line = match (self.format, diagnostic, input) {
+            (Format::Json, _, _) => serde_json::json!({
+                "file": input.map(|input| input.name()),
+                "line": position.map(|(line, _)| line),
+                "column": position.map(|(_, column)| column),
+                "message": message,
+            })
+            .to_string(),
+            (Format::Text, Some(diagnostic), _) => diagnostic.to_string(),
+            (Format::Text, None, Some(input)) => match position {
+                Some((line, column)) => {
+                    format!("error: {}:{}:{}: {}", input.name(), line, column, message)
+                }
+                None => format!("error: {}: {}", input.name(), message),
+            },
+            (Format::Text, None, None) => format!("error: {}", message),
+        };
+
+        // There's nowhere left to report failing to write to standard error.
+        let _ = writeln!(self.stderr, "{}", line);
+    }
+
+    /// Prints the usage, and `problem` with the command line if there is one.
+    fn usage(&mut self, problem: Option<&str>) -> i32 {
+        match problem {
+            Some(problem) => {
+                self.report(None, None, problem, None);
+                let _ = writeln!(self.stderr, "\n{}", USAGE);
+                ERROR
+            }
+            None => {
+                let _ = writeln!(self.stdout, "{}", USAGE);
+                SUCCESS
+            }
+        }
+    }
+}
+
+/// `value` as JSON. Dates and times are strings in RFC 3339 form, durations are strings as they'd
+/// be written in ooml like `"5m30s"`, and byte sizes are numbers of bytes.
+fn to_json(value: &Value<'_>) -> serde_json::Value {
+    use serde_json::Value as Json;
+
+    match value {
+        Value::Null => Json::Null,
+        Value::String(string) => Json::from(string.as_ref()),
+        Value::Integer(integer) => Json::from(*integer),
+        Value::Float(float) => Json::from(*float),
+        Value::Bool(bool) => Json::Bool(*bool),
+        Value::DateTime(date_time) => Json::String(date_time.to_string()),
+        Value::Date(date) => Json::String(date.to_string()),
+        Value::Time(time) => Json::String(time.to_string()),
+        Value::Duration(_) => Json::String(value.to_string()),
+        Value::Bytes(bytes) => Json::from(*bytes),
+        Value::Object(object) => Json::Object(
+            object
+                .iter()
+                .map(|(key, value)| (key.to_string(), to_json(value)))
+                .collect(),
+        ),
+        Value::Array(array) => Json::Array(array.iter().map(to_json).collect()),
+    }
+}
+
+fn exit_code(result: Result<(), i32>) -> i32 {
+    result.err().unwrap_or(SUCCESS)
+}
+
+/// The files named in `args`, or standard input if there are none.
+fn inputs<'a>(args: &[&'a str]) -> Vec<Input<'a>> {
+    if args.is_empty() {
+        return vec![Input::Stdin];
+    }
+
+    args.iter()
+        .map(|&arg| match arg {
+            "-" => Input::Stdin,
+            name => Input::File(name),
+        })
+        .collect()
+}
+
+/// Parses a path like `servers[0].name`, where keys can be quoted like `"a.b"`. Array items can
+/// also be written like keys, as in `servers.0.name`.
+fn parse_path(text: &str) -> Option<Vec<PathSegment<'static>>> {
+    let mut path = Vec::new();
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']')?;
+            path.push(PathSegment::Index(after[..end].parse().ok()?));
+            rest = &after[end + 1..];
+            continue;
+        }
+
+        if !path.is_empty() {
+            rest = rest.strip_prefix('.')?;
+        }
+        let (key, after) = match rest.strip_prefix('"') {
+            Some(quoted) => quoted_key(quoted)?,
+            None => {
+                let end = rest.find(&['.', '['][..]).unwrap_or(rest.len());
+                (rest[..end].to_string(), &rest[end..])
+            }
+        };
+        if key.is_empty() && !rest.starts_with('"') {
+            return None;
+        }
+
+        path.push(PathSegment::Key(Cow::Owned(key)));
+        rest = after;
+    }
+
+    Some(path)
+}
+
+/// The key at the start of `text`, after its opening quote, and the text after its closing
+/// quote. Only `\"`, `\\`, `\n` and `\t` are escapes.
+fn quoted_key(text: &str) -> Option<(String, &str)> {
+    let mut key = String::new();
+    let mut chars = text.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((key, &text[i + 1..])),
+            '\\' => key.push(match chars.next()?.1 {
+                'n' => '\n',
+                't' => '\t',
+                c => c,
+            }),
+            c => key.push(c),
+        }
+    }
+
+    None
+}
+
+/// The value at `path`. Keys that are numbers are indexes into arrays.
+fn lookup<'v>(value: &'v Value<'_>, path: &[PathSegment<'_>]) -> Option<&'v Value<'v>> {
+    let mut value = value;
+    for segment in path {
+        value = match (value, segment) {
+            (Value::Object(object), PathSegment::Key(key)) => object.get(key.as_ref())?,
+            (Value::Object(object), PathSegment::Index(index)) => {
+                object.get(index.to_string().as_str())?
+            }
+            (Value::Array(array), PathSegment::Index(index)) => array.get(*index)?,
+            (Value::Array(array), PathSegment::Key(key)) => {
+                array.get(key.parse::<usize>().ok()?)?
+            }
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+/// A path as it would be written on the command line.
+fn describe(path: &[PathSegment<'_>]) -> String {
+    let mut text = String::new();
+    for segment in path {
+        match segment {
+            PathSegment::Key(key) => {
+                if !text.is_empty() {
+                    text.push('.');
+                }
+                text.push_str(key);
+            }
+            PathSegment::Index(index) => text.push_str(&format!("[{}]", index)),
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    const CONFIG: &str = indoc! {r#"
+        name: "demo"
+        servers:
+          - host: "a"
+            port: 8080
+          - host: "b"
+    "#};
+
+    /// Runs `ooml` with `args` and `stdin`, returning the exit code, standard output and
+    /// standard error.
+    fn run(args: &[&str], stdin: &str) -> (i32, String, String) {
+        let args = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let mut stdin = stdin.as_bytes();
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+
+        let code = Cli {
+            format: Format::Text,
+            stdin: &mut stdin,
+            stdout: &mut stdout,
+            stderr: &mut stderr,
+        }
+        .run(&args);
+        (
+            code,
+            String::from_utf8(stdout).unwrap(),
+            String::from_utf8(stderr).unwrap(),
+        )
+    }
+
+    /// A file in the temporary directory with `contents`, removed when it's dropped.
+    struct TempFile(String);
+
+    impl TempFile {
+        fn new(name: &str, contents: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("ooml-{}-{}", process::id(), name));
+            fs::write(&path, contents).unwrap();
+            TempFile(path.to_str().unwrap().to_string())
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn check() {
+        assert_eq!(
+            run(&["check"], CONFIG),
+            (SUCCESS, String::new(), String::new())
+        );
+        assert_eq!(
+            run(&["check"], "# commented out: 1\n"),
+            (SUCCESS, String::new(), String::new())
+        );
+
+        let (code, stdout, stderr) = run(&["check"], "a: :\n");
+        assert_eq!((code, stdout.as_str()), (FAILURE, ""));
+        assert!(stderr.starts_with("error: expected a value\n --> <stdin>:1:4\n"));
+
+        let valid = TempFile::new("valid.ooml", CONFIG);
+        let invalid = TempFile::new("invalid.ooml", "a: 1\na: 2\n");
+        let (code, _, stderr) = run(&["check", &valid.0, &invalid.0], "");
+        assert_eq!(code, FAILURE);
+        assert!(
+            stderr.contains(&format!("--> {}:2:1", invalid.0)),
+            "{}",
+            stderr
+        );
+
+        let (code, _, stderr) = run(&["check", "/nonexistent/ooml"], "");
+        assert_eq!(code, ERROR);
+        assert!(
+            stderr.starts_with("error: /nonexistent/ooml: "),
+            "{}",
+            stderr
+        );
+    }
+
+    #[test]
+    fn json_errors() {
+        let (code, _, stderr) = run(&["--format=json", "check"], "a: :\n");
+        assert_eq!(code, FAILURE);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&stderr).unwrap(),
+            serde_json::json!({
+                "file": "<stdin>",
+                "line": 1,
+                "column": 4,
+                "message": "expected a value",
+            })
+        );
+
+        let (code, _, stderr) = run(&["get", "--format=json", "missing"], CONFIG);
+        assert_eq!(code, FAILURE);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&stderr).unwrap(),
+            serde_json::json!({
+                "file": "<stdin>",
+                "line": null,
+                "column": null,
+                "message": "there's no value at `missing`",
+            })
+        );
+    }
+
+    #[test]
+    fn fmt() {
+        let messy = "name:   \"demo\"\nserver:\n    port: 80\n";
+        let formatted = "name: \"demo\"\n\nserver:\n  port: 80\n";
+        assert_eq!(
+            run(&["fmt"], messy),
+            (SUCCESS, formatted.to_string(), String::new())
+        );
+        assert_eq!(
+            run(&["fmt", "--check"], messy),
+            (FAILURE, "<stdin>\n".to_string(), String::new())
+        );
+        assert_eq!(run(&["fmt", "--check"], formatted).0, SUCCESS);
+
+        let file = TempFile::new("fmt.ooml", messy);
+        assert_eq!(
+            run(&["fmt", &file.0], ""),
+            (SUCCESS, String::new(), String::new())
+        );
+        assert_eq!(fs::read_to_string(&file.0).unwrap(), formatted);
+    }
+
+    #[test]
+    fn json() {
+        let (code, json, _) = run(&["to-json"], CONFIG);
+        assert_eq!(code, SUCCESS);
+        assert_eq!(
+            json,
+            indoc! {r#"
+                {
+                  "name": "demo",
+                  "servers": [
+                    {
+                      "host": "a",
+                      "port": 8080
+                    },
+                    {
+                      "host": "b"
+                    }
+                  ]
+                }
+            "#}
+        );
+        assert_eq!(
+            run(&["from-json"], &json),
+            (SUCCESS, CONFIG.to_string(), String::new())
+        );
+
+        let (code, json, _) = run(
+            &["to-json"],
+            "timeout: 5m30s\nsize: 1KiB\nat: 2024-01-31T09:30:00Z\nday: 2024-01-31\n",
+        );
+        assert_eq!(code, SUCCESS);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            serde_json::json!({
+                "timeout": "5m30s",
+                "size": 1024,
+                "at": "2024-01-31T09:30:00Z",
+                "day": "2024-01-31",
+            })
+        );
+
+        let (code, _, stderr) = run(&["from-json"], "[1,");
+        assert_eq!(code, FAILURE);
+        assert!(stderr.starts_with("error: <stdin>:1:3: "), "{}", stderr);
+        let (code, _, stderr) = run(&["from-json"], "1");
+        assert_eq!(code, FAILURE);
+        assert!(
+            stderr.contains("only an object or a non-empty array"),
+            "{}",
+            stderr
+        );
+    }
+
+    #[test]
+    fn get() {
+        let get = |path| run(&["get", path], CONFIG);
+        assert_eq!(get("name"), (SUCCESS, "demo\n".to_string(), String::new()));
+        assert_eq!(get("servers[0].port").1, "8080\n");
+        assert_eq!(get("servers.1.host").1, "b\n");
+        assert_eq!(get("servers[1]").1, "host: \"b\"\n");
+        assert_eq!(get("").1, CONFIG);
+        assert_eq!(
+            get("servers[2]"),
+            (
+                FAILURE,
+                String::new(),
+                "error: <stdin>: there's no value at `servers[2]`\n".to_string()
+            )
+        );
+        assert_eq!(get("servers[x]").0, ERROR);
+    }
+
+    #[test]
+    fn paths() {
+        let key = |key: &str| PathSegment::Key(Cow::Owned(key.to_string()));
+        assert_eq!(parse_path(""), Some(vec![]));
+        assert_eq!(
+            parse_path("a.b[0][1].c"),
+            Some(vec![
+                key("a"),
+                key("b"),
+                PathSegment::Index(0),
+                PathSegment::Index(1),
+                key("c")
+            ])
+        );
+        assert_eq!(
+            parse_path(r#""a.b"."c\"d""#),
+            Some(vec![key("a.b"), key("c\"d")])
+        );
+        assert_eq!(parse_path(r#""""#), Some(vec![key("")]));
+        assert_eq!(parse_path("a..b"), None);
+        assert_eq!(parse_path("a."), None);
+        assert_eq!(parse_path("a[0"), None);
+        assert_eq!(parse_path("a\"b\""), Some(vec![key("a\"b\"")]));
+    }
+
+    #[test]
+    fn usage() {
+        let (code, stdout, _) = run(&["--help"], "");
+        assert_eq!((code, stdout), (SUCCESS, format!("{}\n", USAGE)));
+
+        for args in [
+            &[][..],
+            &["lint"],
+            &["fmt", "--write"],
+            &["check", "--check"],
+            &["get"],
+            &["to-json", "a", "b"],
+        ]
+        .iter()
+        {
+            let (code, stdout, stderr) = run(args, "");
+            assert_eq!((code, stdout.as_str()), (ERROR, ""), "{:?}", args);
+            assert!(stderr.ends_with(&format!("\n{}\n", USAGE)), "{:?}", args);
+        }
+    }
+}
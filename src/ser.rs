@@ -0,0 +1,584 @@
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::fmt;
+
+use serde::ser::{self, Serialize};
+
+use crate::{emit, Error, Map, Value};
+
+/// Serializes `value` as ooml text that [`parse`](crate::parse) reads back as the same value.
+/// Objects are written as indented blocks and arrays of scalars that fit on a line as flow
+/// arrays, with keys quoted only when they have to be.
+///
+/// Only types that serialize as a map, struct or non-empty sequence can be written, as those are
+/// the only documents ooml has. Integers have to fit in an `i64`, and floats have to be finite.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+    emit(&to_value(value)?)
+}
+
+/// Serializes `value` as a [`Value`]. Enums are externally tagged, the same as [`from_str`]
+/// reads them: unit variants are strings and other variants are objects with the variant as
+/// their only key.
+///
+/// [`from_str`]: crate::from_str
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value<'static>, Error> {
+    value.serialize(Serializer)
+}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Error::Serialize(message.to_string())
+    }
+}
+
+struct Serializer;
+
+/// An object with `variant` as its only key, for an enum variant with data.
+fn variant(variant: &'static str, value: Value<'static>) -> Value<'static> {
+    let mut object = Map::new();
+    object.insert(Cow::Borrowed(variant), value);
+    Value::Object(object)
+}
+
+fn integer<T: fmt::Display + Copy>(integer: T) -> Result<Value<'static>, Error>
+where
+    i64: TryFrom<T>,
+{
+    i64::try_from(integer)
+        .map(Value::Integer)
+        .map_err(|_| Error::Serialize(format!("{} is out of the range of integers", integer)))
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = Value<'static>;
+    type Error = Error;
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeVariant<SerializeArray>;
+    type SerializeMap = SerializeObject;
+    type SerializeStruct = SerializeObject;
+    type SerializeStructVariant = SerializeVariant<SerializeObject>;
+
+    fn serialize_bool(self, v: bool) -> Result<Value<'static>, Error> {
+        Ok(Value::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value<'static>, Error> {
+        integer(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value<'static>, Error> {
+        integer(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value<'static>, Error> {
+        integer(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value<'static>, Error> {
+        integer(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Value<'static>, Error> {
+        integer(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value<'static>, Error> {
+        integer(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value<'static>, Error> {
+        integer(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value<'static>, Error> {
+        integer(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value<'static>, Error> {
+        integer(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Value<'static>, Error> {
+        integer(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value<'static>, Error> {
+        Ok(Value::Float(v.into()))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value<'static>, Error> {
+        Ok(Value::Float(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value<'static>, Error> {
+        Ok(Value::String(Cow::Owned(v.to_string())))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value<'static>, Error> {
+        Ok(Value::String(Cow::Owned(v.to_string())))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value<'static>, Error> {
+        Ok(Value::Array(
+            v.iter().map(|&byte| Value::Integer(byte.into())).collect(),
+        ))
+    }
+
+    fn serialize_none(self) -> Result<Value<'static>, Error> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value<'static>, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value<'static>, Error> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value<'static>, Error> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Value<'static>, Error> {
+        Ok(Value::String(Cow::Borrowed(variant)))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value<'static>, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value<'static>, Error> {
+        Ok(self::variant(variant, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeArray, Error> {
+        Ok(SerializeArray {
+            array: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeArray, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeArray, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVariant<SerializeArray>, Error> {
+        Ok(SerializeVariant {
+            variant,
+            inner: self.serialize_seq(Some(len))?,
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeObject, Error> {
+        Ok(SerializeObject {
+            object: Map::with_capacity(len.unwrap_or(0)),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeObject, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVariant<SerializeObject>, Error> {
+        Ok(SerializeVariant {
+            variant,
+            inner: self.serialize_map(Some(len))?,
+        })
+    }
+}
+
+struct SerializeArray {
+    array: Vec<Value<'static>>,
+}
+
+impl ser::SerializeSeq for SerializeArray {
+    type Ok = Value<'static>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.array.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value<'static>, Error> {
+        Ok(Value::Array(self.array))
+    }
+}
+
+impl ser::SerializeTuple for SerializeArray {
+    type Ok = Value<'static>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value<'static>, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeArray {
+    type Ok = Value<'static>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value<'static>, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+struct SerializeObject {
+    object: Map<'static>,
+    /// The key of the entry whose value is serialized next.
+    key: Option<Cow<'static, str>>,
+}
+
+impl ser::SerializeMap for SerializeObject {
+    type Ok = Value<'static>;
+    type Error = Error;
+
+    /// Keys can be anything that serializes as a string, integer or boolean, which are written as
+    /// strings.
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        let key = match key.serialize(Serializer)? {
+            Value::String(key) => key,
+            Value::Integer(key) => Cow::Owned(key.to_string()),
+            Value::Bool(key) => Cow::Owned(key.to_string()),
+            _ => return Err(Error::Serialize("keys have to be strings".to_string())),
+        };
+        self.key = Some(key);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error::Serialize("value serialized before its key".to_string()))?;
+        self.object.insert(key, value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value<'static>, Error> {
+        Ok(Value::Object(self.object))
+    }
+}
+
+impl ser::SerializeStruct for SerializeObject {
+    type Ok = Value<'static>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.object
+            .insert(Cow::Borrowed(key), value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value<'static>, Error> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+/// A tuple or struct variant, which is written as an object with the variant as its only key.
+struct SerializeVariant<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl ser::SerializeTupleVariant for SerializeVariant<SerializeArray> {
+    type Ok = Value<'static>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<Value<'static>, Error> {
+        Ok(variant(self.variant, ser::SerializeSeq::end(self.inner)?))
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeVariant<SerializeObject> {
+    type Ok = Value<'static>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        ser::SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<Value<'static>, Error> {
+        Ok(variant(self.variant, ser::SerializeMap::end(self.inner)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use indoc::indoc;
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{from_str, parse};
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Config {
+        name: String,
+        port: u16,
+        ratio: f64,
+        tags: Vec<String>,
+        limits: Option<Limits>,
+        fallback: Option<String>,
+        servers: Vec<Server>,
+        matrix: Vec<Vec<u8>>,
+        labels: BTreeMap<String, String>,
+        modes: Vec<Mode>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Limits {
+        requests: u32,
+        burst: u32,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Server {
+        host: String,
+        ports: Vec<u16>,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    enum Mode {
+        Off,
+        Fixed(u32),
+        Range(u32, u32),
+        Custom { min: u32, max: u32 },
+    }
+
+    fn config() -> Config {
+        Config {
+            name: "api \"v2\"".to_string(),
+            port: 8080,
+            ratio: 2.0,
+            tags: vec!["a".to_string(), "b".to_string()],
+            limits: Some(Limits {
+                requests: 100,
+                burst: 10,
+            }),
+            fallback: None,
+            servers: vec![
+                Server {
+                    host: "a".to_string(),
+                    ports: vec![80, 443],
+                },
+                Server {
+                    host: "b".to_string(),
+                    ports: vec![],
+                },
+            ],
+            matrix: vec![vec![1, 2], vec![]],
+            labels: vec![
+                ("team".to_string(), "core".to_string()),
+                ("x-request-id".to_string(), "1".to_string()),
+                ("has.dot".to_string(), "2".to_string()),
+            ]
+            .into_iter()
+            .collect(),
+            modes: vec![
+                Mode::Off,
+                Mode::Fixed(1),
+                Mode::Range(1, 2),
+                Mode::Custom { min: 1, max: 2 },
+            ],
+        }
+    }
+
+    #[test]
+    fn block_style() {
+        assert_eq!(
+            to_string(&config()).unwrap(),
+            indoc! {r#"
+                name: "api \"v2\""
+                port: 8080
+                ratio: 2.0
+                tags: ["a", "b"]
+                limits:
+                  requests: 100
+                  burst: 10
+                fallback: null
+                servers:
+                  - host: "a"
+                    ports: [80, 443]
+                  - host: "b"
+                    ports: []
+                matrix:
+                  - [1, 2]
+                  - []
+                labels:
+                  "has.dot": "2"
+                  team: "core"
+                  x-request-id: "1"
+                modes:
+                  - "Off"
+                  - Fixed: 1
+                  - Range: [1, 2]
+                  - Custom:
+                      min: 1
+                      max: 2
+            "#}
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let config = config();
+        let text = to_string(&config).unwrap();
+
+        assert_eq!(parse(&text).unwrap(), to_value(&config).unwrap());
+        assert_eq!(from_str::<Config>(&text).unwrap(), config);
+    }
+
+    #[test]
+    fn long_arrays_are_blocks() {
+        let numbers = (0..30).collect::<Vec<u32>>();
+        let text = to_string(&vec![numbers.clone()]).unwrap();
+
+        assert!(text.starts_with("- - 0\n  - 1\n"), "{}", text);
+        assert_eq!(from_str::<Vec<Vec<u32>>>(&text).unwrap(), vec![numbers]);
+    }
+
+    #[test]
+    fn keys() {
+        let keys = [
+            "bare",
+            "with space",
+            "dash-and_underscore",
+            "ünïcode",
+            "",
+            " padded",
+            "- item",
+            "a.b",
+            "a:b",
+            "#",
+            "\"",
+        ];
+        let map = keys
+            .iter()
+            .map(|key| (key.to_string(), 1))
+            .collect::<BTreeMap<_, _>>();
+        let text = to_string(&map).unwrap();
+
+        for key in &keys[..4] {
+            assert!(text.contains(&format!("\n{}: 1", key)), "{}", text);
+        }
+        assert_eq!(from_str::<BTreeMap<String, u8>>(&text).unwrap(), map);
+
+        let map = vec![(1, "a"), (2, "b")]
+            .into_iter()
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(to_string(&map).unwrap(), "1: \"a\"\n2: \"b\"\n");
+        assert_eq!(
+            from_str::<BTreeMap<u32, &str>>(&to_string(&map).unwrap()).unwrap(),
+            map
+        );
+
+        let map = vec![(-1i64, 1), (10, 2)]
+            .into_iter()
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(
+            from_str::<BTreeMap<i64, u8>>(&to_string(&map).unwrap()).unwrap(),
+            map
+        );
+        let map = vec![(false, 1), (true, 2)]
+            .into_iter()
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(to_string(&map).unwrap(), "false: 1\ntrue: 2\n");
+        assert_eq!(
+            from_str::<BTreeMap<bool, u8>>(&to_string(&map).unwrap()).unwrap(),
+            map
+        );
+        assert!(from_str::<BTreeMap<u8, u8>>("256: 1\n").is_err());
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            to_string(&1),
+            Err(Error::Serialize(
+                "only an object or a non-empty array can be written as a document".to_string()
+            ))
+        );
+        assert!(to_string(&Vec::<u8>::new()).is_err());
+        // An empty document, which parses back as an empty map.
+        assert_eq!(to_string(&BTreeMap::<String, u8>::new()), Ok(String::new()));
+        assert_eq!(
+            to_string(&[u64::MAX]),
+            Err(Error::Serialize(
+                "18446744073709551615 is out of the range of integers".to_string()
+            ))
+        );
+        assert_eq!(
+            to_string(&[f64::NAN]),
+            Err(Error::Serialize(
+                "NaN can't be written, as numbers have to be finite".to_string()
+            ))
+        );
+
+        let mut map = BTreeMap::new();
+        map.insert(vec![1], 1);
+        assert_eq!(
+            to_string(&map),
+            Err(Error::Serialize("keys have to be strings".to_string()))
+        );
+    }
+}